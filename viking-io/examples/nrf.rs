@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use std::{error::Error, sync::Arc};
 
 use futures_lite::future::block_on;
@@ -64,7 +66,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     nrf.write_reg(SETUP_AW, 0b10);
 
     nrf.write_reg_bytes(TX_ADDR, &[0x23, 0x45, 0x23, 0xC1]);
-    nrf.write_reg(RX_PW_P0 as u8, pkt_len);
+    nrf.write_reg(RX_PW_P0, pkt_len);
 
     nrf.write_reg(RF_CH, channel);
     nrf.write_reg(RF_SETUP, RF_SETUP_DR_LOW | RF_SETUP_PWR_0DBM);
@@ -145,7 +147,7 @@ impl NrfSpi {
 
         block_on(
             self.spi
-                .transaction(&mut [Operation::Write(&cmd), Operation::Write(&data)]),
+                .transaction(&mut [Operation::Write(&cmd), Operation::Write(data)]),
         )
         .unwrap();
     }
@@ -0,0 +1,495 @@
+use embedded_can::{ExtendedId, Frame as _, Id, StandardId};
+use futures_lite::{Stream, stream};
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, Raw, SliceResponse},
+};
+use viking_protocol::protocol::can as protocol;
+
+/// Data lengths allowed in CAN FD frames.
+const FD_LENGTHS: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// CAN 2.0 or CAN FD frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    id: Id,
+    flags: u8,
+    len: u8,
+    data: [u8; 64],
+}
+
+impl Frame {
+    /// Create a CAN FD frame with up to 64 bytes of data, optionally using the
+    /// faster data bitrate for the data phase.
+    pub fn new_fd(id: impl Into<Id>, data: &[u8], bitrate_switch: bool) -> Option<Self> {
+        if !FD_LENGTHS.contains(&data.len()) {
+            return None;
+        }
+        let mut flags = protocol::frame::FD;
+        if bitrate_switch {
+            flags |= protocol::frame::BRS;
+        }
+        let mut frame = Frame {
+            id: id.into(),
+            flags,
+            len: data.len() as u8,
+            data: [0; 64],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    pub fn is_fd(&self) -> bool {
+        self.flags & protocol::frame::FD != 0
+    }
+
+    pub fn bitrate_switch(&self) -> bool {
+        self.flags & protocol::frame::BRS != 0
+    }
+
+    fn raw_id(&self) -> u32 {
+        match self.id {
+            Id::Standard(id) => id.as_raw() as u32,
+            Id::Extended(id) => id.as_raw() | protocol::ID_EXTENDED,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(protocol::frame::HEADER_LEN + self.len as usize);
+        buf.extend_from_slice(&self.raw_id().to_le_bytes());
+        buf.push(self.flags);
+        buf.push(self.len);
+        if !self.is_remote_frame() {
+            buf.extend_from_slice(self.data());
+        }
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let raw_id = u32::from_le_bytes(buf.get(0..4)?.try_into().unwrap());
+        let flags = *buf.get(4)?;
+        let len = *buf.get(5)?;
+        let id = if raw_id & protocol::ID_EXTENDED != 0 {
+            Id::Extended(ExtendedId::new(raw_id & !protocol::ID_EXTENDED)?)
+        } else {
+            Id::Standard(StandardId::new(raw_id.try_into().ok()?)?)
+        };
+        let mut frame = Frame {
+            id,
+            flags,
+            len: len.min(64),
+            data: [0; 64],
+        };
+        if !frame.is_remote_frame() {
+            let data = buf.get(6..6 + frame.len as usize)?;
+            frame.data[..data.len()].copy_from_slice(data);
+        }
+        Some(frame)
+    }
+}
+
+impl embedded_can::Frame for Frame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        let mut frame = Frame {
+            id: id.into(),
+            flags: 0,
+            len: data.len() as u8,
+            data: [0; 64],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(Frame {
+            id: id.into(),
+            flags: protocol::frame::REMOTE,
+            len: dlc as u8,
+            data: [0; 64],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.flags & protocol::frame::REMOTE != 0
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        self.len as usize
+    }
+
+    fn data(&self) -> &[u8] {
+        if self.is_remote_frame() {
+            &[]
+        } else {
+            &self.data[..self.len as usize]
+        }
+    }
+}
+
+/// Acceptance filter matching frames where the identifier bits selected by
+/// `mask` are equal to those of `id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Filter {
+    pub id: Id,
+    pub mask: u32,
+}
+
+impl Filter {
+    /// Accept only frames with exactly the identifier `id`.
+    pub fn exact(id: impl Into<Id>) -> Self {
+        let id = id.into();
+        let mask = match id {
+            Id::Standard(_) => StandardId::MAX.as_raw() as u32,
+            Id::Extended(_) => ExtendedId::MAX.as_raw(),
+        };
+        Filter { id, mask }
+    }
+
+    fn to_protocol(self) -> protocol::Filter {
+        let (id, mask) = match self.id {
+            Id::Standard(id) => (id.as_raw() as u32, self.mask),
+            Id::Extended(id) => (id.as_raw() | protocol::ID_EXTENDED, self.mask),
+        };
+        protocol::Filter {
+            id: id.into(),
+            mask: (mask | protocol::ID_EXTENDED).into(),
+        }
+    }
+}
+
+/// Bus error condition reported by the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusError {
+    Bit,
+    Stuff,
+    Crc,
+    Form,
+    Acknowledge,
+    /// Received frames were lost because the device's buffer was full.
+    Overrun,
+    /// The controller entered the bus-off state.
+    BusOff,
+    Other,
+}
+
+impl BusError {
+    fn from_code(code: u8) -> Self {
+        match code {
+            protocol::error::BIT => BusError::Bit,
+            protocol::error::STUFF => BusError::Stuff,
+            protocol::error::CRC => BusError::Crc,
+            protocol::error::FORM => BusError::Form,
+            protocol::error::ACK => BusError::Acknowledge,
+            protocol::error::OVERRUN => BusError::Overrun,
+            protocol::error::BUS_OFF => BusError::BusOff,
+            _ => BusError::Other,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("bus error: {0:?}")]
+    Bus(BusError),
+
+    #[error("controller is bus-off")]
+    BusOff,
+
+    #[error("transmit timeout")]
+    Timeout,
+
+    #[error("{0}")]
+    Request(RequestError),
+}
+
+impl From<RequestError> for Error {
+    fn from(v: RequestError) -> Self {
+        use viking_protocol::errors;
+        match v {
+            RequestError::Status(errors::ERR_INVALID_STATE) => Self::BusOff,
+            RequestError::Status(errors::ERR_TIMEOUT) => Self::Timeout,
+            v => Self::Request(v),
+        }
+    }
+}
+
+impl embedded_can::Error for Error {
+    fn kind(&self) -> embedded_can::ErrorKind {
+        use embedded_can::ErrorKind;
+
+        match self {
+            Error::Bus(BusError::Bit) => ErrorKind::Bit,
+            Error::Bus(BusError::Stuff) => ErrorKind::Stuff,
+            Error::Bus(BusError::Crc) => ErrorKind::Crc,
+            Error::Bus(BusError::Form) => ErrorKind::Form,
+            Error::Bus(BusError::Acknowledge) => ErrorKind::Acknowledge,
+            Error::Bus(BusError::Overrun) => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Error confinement state of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    ErrorActive,
+    ErrorPassive,
+    BusOff,
+}
+
+/// Controller state and error counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub state: State,
+    pub tx_errors: u8,
+    pub rx_errors: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanEvent {
+    Frame {
+        timestamp: u32,
+        frame: Frame,
+    },
+    Error {
+        timestamp: u32,
+        error: BusError,
+        tx_errors: u8,
+        rx_errors: u8,
+    },
+}
+
+/// CAN bus controller.
+pub struct Can {
+    resource: Resource,
+    timestamp_clock: u32,
+}
+
+pub struct CanBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+    filters: Vec<protocol::Filter>,
+}
+
+impl ResourceMode for Can {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = CanBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        CanBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl CanBuilder {
+    fn set_flag(&mut self, flag: protocol::ConfigFlags, value: bool) {
+        self.config.flags = if value {
+            self.config.flags.union(flag)
+        } else {
+            self.config.flags.difference(flag)
+        };
+    }
+
+    /// Nominal bitrate in bits per second. Defaults to 500 kbit/s.
+    pub fn bitrate(mut self, bitrate: u32) -> Self {
+        self.config.bitrate.set(bitrate);
+        self
+    }
+
+    /// Enable CAN FD, using `bitrate` for the data phase of frames with the
+    /// bitrate switch flag set.
+    pub fn fd(mut self, data_bitrate: u32) -> Self {
+        self.set_flag(protocol::ConfigFlags::FD, true);
+        self.config.data_bitrate.set(data_bitrate);
+        self
+    }
+
+    /// Receive without acknowledging frames or transmitting.
+    pub fn listen_only(mut self) -> Self {
+        self.set_flag(protocol::ConfigFlags::LISTEN_ONLY, true);
+        self
+    }
+
+    /// Receive transmitted frames internally without driving the bus.
+    pub fn loopback(mut self) -> Self {
+        self.set_flag(protocol::ConfigFlags::LOOPBACK, true);
+        self
+    }
+
+    /// Add an acceptance filter. If no filters are added, all frames are
+    /// received.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter.to_protocol());
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<Can, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let bitrate = self.config.bitrate.get();
+        if bitrate < desc.min_bitrate.get() || bitrate > desc.max_bitrate.get() {
+            Err("bitrate not supported")?
+        }
+        if self.config.flags.contains(protocol::ConfigFlags::FD)
+            && (!desc.flags.contains(protocol::ModeFlags::FD)
+                || self.config.data_bitrate.get() > desc.max_data_bitrate.get())
+        {
+            Err("CAN FD or data bitrate not supported")?
+        }
+        if self
+            .config
+            .flags
+            .contains(protocol::ConfigFlags::LISTEN_ONLY)
+            && !desc.flags.contains(protocol::ModeFlags::LISTEN_ONLY)
+        {
+            Err("listen-only mode not supported")?
+        }
+        if self.config.flags.contains(protocol::ConfigFlags::LOOPBACK)
+            && !desc.flags.contains(protocol::ModeFlags::LOOPBACK)
+        {
+            Err("loopback mode not supported")?
+        }
+        if self.filters.len() > desc.filter_count as usize {
+            Err("too many filters")?
+        }
+        self.config.filter_count = self.filters.len() as u8;
+
+        let mut config = self.config.as_bytes().to_vec();
+        config.extend_from_slice(self.filters.as_bytes());
+
+        let mut resource = self.resource;
+        resource.configure(self.mode, &config).await?;
+        resource
+            .subscribe_events(|evt, data| match evt {
+                protocol::evt::RX => {
+                    let flags = *data.get(8)?;
+                    let len = *data.get(9)? as usize;
+                    if flags & protocol::frame::REMOTE != 0 {
+                        Some(4 + protocol::frame::HEADER_LEN)
+                    } else {
+                        Some(4 + protocol::frame::HEADER_LEN + len)
+                    }
+                }
+                _ => Some(7),
+            })
+            .await;
+
+        Ok(Can {
+            resource,
+            timestamp_clock: desc.timestamp_clock.get(),
+        })
+    }
+}
+
+impl Can {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Frequency in Hz of the counter used for timestamps.
+    pub fn timestamp_clock(&self) -> u32 {
+        self.timestamp_clock
+    }
+
+    pub fn cmd_transmit<'a>(&self, encoded: &'a [u8]) -> Command<Raw<'a>, ()> {
+        Command::new(self.resource.id, protocol::cmd::TRANSMIT, Raw(encoded), ())
+    }
+
+    pub fn cmd_status(&self) -> Command<(), SliceResponse> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::STATUS,
+            (),
+            SliceResponse::new(3),
+        )
+    }
+
+    /// Queue a frame for transmission, waiting for space in the device's
+    /// transmit buffer.
+    pub async fn transmit(&self, frame: &Frame) -> Result<(), Error> {
+        let encoded = frame.encode();
+        Ok(self
+            .resource
+            .interface
+            .run(self.cmd_transmit(&encoded))
+            .await?)
+    }
+
+    pub async fn status(&self) -> Result<Status, RequestError> {
+        let mut batch = self.resource.interface.batch();
+        let status = batch.push(self.cmd_status());
+        let res = batch.run().await?;
+        let res = res.get(status)?;
+        let state = match res[0] {
+            protocol::state::ERROR_ACTIVE => State::ErrorActive,
+            protocol::state::ERROR_PASSIVE => State::ErrorPassive,
+            _ => State::BusOff,
+        };
+        Ok(Status {
+            state,
+            tx_errors: res[1],
+            rx_errors: res[2],
+        })
+    }
+
+    /// Wait for the next received frame or bus error.
+    pub async fn next_event(&self) -> Result<CanEvent, RequestError> {
+        let event = self.resource.next_event().await?;
+        let timestamp = u32::from_le_bytes(event.data[0..4].try_into().unwrap());
+        match event.evt {
+            protocol::evt::RX => {
+                let frame = Frame::decode(&event.data[4..])
+                    .ok_or(RequestError::Protocol("invalid CAN frame"))?;
+                Ok(CanEvent::Frame { timestamp, frame })
+            }
+            protocol::evt::ERROR => Ok(CanEvent::Error {
+                timestamp,
+                error: BusError::from_code(event.data[4]),
+                tx_errors: event.data[5],
+                rx_errors: event.data[6],
+            }),
+            _ => Err(RequestError::Protocol("unknown CAN event")),
+        }
+    }
+
+    /// Wait for the next received frame and its timestamp, returning bus
+    /// errors as [`Error::Bus`].
+    pub async fn receive(&self) -> Result<(u32, Frame), Error> {
+        match self.next_event().await? {
+            CanEvent::Frame { timestamp, frame } => Ok((timestamp, frame)),
+            CanEvent::Error { error, .. } => Err(Error::Bus(error)),
+        }
+    }
+
+    pub fn events(&self) -> impl Stream<Item = Result<CanEvent, RequestError>> + '_ {
+        stream::unfold(
+            self,
+            |this| async move { Some((this.next_event().await, this)) },
+        )
+    }
+}
@@ -3,6 +3,9 @@ use std::marker::PhantomData;
 pub trait ResponsePattern: Clone {
     type Output<'a>;
     fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn output<'a>(&self, status: u8, buf: &'a [u8]) -> Self::Output<'a>;
 }
 
@@ -44,6 +47,12 @@ impl<T> ScalarResponse<T> {
     }
 }
 
+impl<T> Default for ScalarResponse<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ResponsePattern for ScalarResponse<u8> {
     type Output<'a> = u8;
 
@@ -64,12 +73,30 @@ impl StaticResponsePattern for ScalarResponse<u8> {
     }
 }
 
+impl ResponsePattern for ScalarResponse<u32> {
+    type Output<'a> = u32;
+
+    fn output(&self, _status: u8, buf: &[u8]) -> u32 {
+        u32::from_le_bytes(buf[..4].try_into().unwrap())
+    }
+
+    fn len(&self) -> usize {
+        size_of::<u32>()
+    }
+}
+
+impl StaticResponsePattern for ScalarResponse<u32> {
+    type StaticOutput = u32;
+
+    fn static_output(&self, status: u8, buf: &[u8]) -> u32 {
+        self.output(status, buf)
+    }
+}
+
 impl ResponsePattern for () {
     type Output<'a> = ();
 
-    fn output(&self, _status: u8, _buf: &[u8]) -> () {
-        ()
-    }
+    fn output(&self, _status: u8, _buf: &[u8]) {}
 
     fn len(&self) -> usize {
         size_of::<()>()
@@ -79,7 +106,7 @@ impl ResponsePattern for () {
 impl StaticResponsePattern for () {
     type StaticOutput = ();
 
-    fn static_output(&self, status: u8, buf: &[u8]) -> () {
+    fn static_output(&self, status: u8, buf: &[u8]) {
         self.output(status, buf)
     }
 }
@@ -105,10 +132,43 @@ impl ResponsePattern for SliceResponse {
     }
 }
 
+/// A response of fixed-length data, returned along with the successful status byte.
+#[derive(Clone)]
+pub struct StatusSliceResponse(usize);
+
+impl StatusSliceResponse {
+    pub(crate) fn new(len: usize) -> Self {
+        Self(len)
+    }
+}
+
+impl ResponsePattern for StatusSliceResponse {
+    type Output<'a> = (u8, &'a [u8]);
+
+    fn output<'a>(&self, status: u8, buf: &'a [u8]) -> (u8, &'a [u8]) {
+        (status, &buf[..self.0])
+    }
+
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+impl StaticResponsePattern for StatusSliceResponse {
+    type StaticOutput = (u8, Vec<u8>);
+
+    fn static_output(&self, status: u8, buf: &[u8]) -> (u8, Vec<u8>) {
+        (status, buf[..self.0].to_vec())
+    }
+}
+
 pub trait PayloadPattern {
     fn len(&self) -> usize {
         self.bytes().count()
     }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn bytes(&self) -> impl Iterator<Item = u8>;
 }
 
@@ -124,6 +184,24 @@ impl PayloadPattern for u16 {
     }
 }
 
+impl PayloadPattern for u32 {
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        self.to_le_bytes().into_iter()
+    }
+}
+
+impl<A: PayloadPattern, B: PayloadPattern> PayloadPattern for (A, B) {
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        self.0.bytes().chain(self.1.bytes())
+    }
+}
+
+impl<A: PayloadPattern, B: PayloadPattern, C: PayloadPattern> PayloadPattern for (A, B, C) {
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        self.0.bytes().chain(self.1.bytes()).chain(self.2.bytes())
+    }
+}
+
 impl PayloadPattern for () {
     fn bytes(&self) -> impl Iterator<Item = u8> {
         [].into_iter()
@@ -143,6 +221,54 @@ impl PayloadPattern for &[u8] {
     }
 }
 
+impl PayloadPattern for &[u16] {
+    fn len(&self) -> usize {
+        1 + <[u16]>::len(self).min(255) * 2
+    }
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        [<[u16]>::len(self)
+            .try_into()
+            .expect("slice must be less than 256 words")]
+        .into_iter()
+        .chain(self.iter().flat_map(|w| w.to_le_bytes()))
+    }
+}
+
+impl PayloadPattern for &[u32] {
+    fn len(&self) -> usize {
+        1 + <[u32]>::len(self).min(255) * 4
+    }
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        [<[u32]>::len(self)
+            .try_into()
+            .expect("slice must be less than 256 words")]
+        .into_iter()
+        .chain(self.iter().flat_map(|w| w.to_le_bytes()))
+    }
+}
+
+/// Bytes sent without a length prefix, for payloads whose length is implied
+/// by a preceding field.
+pub struct Raw<'a>(pub &'a [u8]);
+
+impl PayloadPattern for Raw<'_> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        self.0.iter().copied()
+    }
+}
+
+impl<const N: usize> PayloadPattern for [u8; N] {
+    fn len(&self) -> usize {
+        N
+    }
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        self.iter().copied()
+    }
+}
+
 pub struct Command<P, R> {
     pub(crate) resource: u8,
     pub(crate) cmd: u8,
@@ -175,4 +301,10 @@ where
     pub(crate) fn response(&self) -> R {
         self.response.clone()
     }
+
+    /// Bytes taken by the command in a request batch and by its status and
+    /// response in the response batch.
+    pub(crate) fn encoded_len(&self) -> (usize, usize) {
+        (1 + self.payload.len(), 1 + self.response.len())
+    }
 }
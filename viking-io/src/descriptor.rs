@@ -21,6 +21,7 @@ pub struct Mode {
 }
 
 impl Resources {
+    #[allow(clippy::result_unit_err)]
     pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
         use viking_protocol::descriptor::*;
         let mut viking = VikingDescriptor::new_zeroed();
@@ -156,7 +157,7 @@ impl Mode {
 
 fn descriptors(mut bytes: &[u8]) -> impl Iterator<Item = Result<(u8, &[u8]), ()>> {
     iter::from_fn(move || {
-        if bytes.len() == 0 {
+        if bytes.is_empty() {
             return None;
         }
         if bytes.len() < 2 {
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use log::{debug, warn};
+use nusb::{
+    Endpoint,
+    transfer::{Buffer, Bulk, In},
+};
+
+use crate::RequestError;
+
+const EVENT_TRANSFER_SIZE: usize = 4096;
+
+/// Determines the length of an event's data from the event number and the
+/// bytes received so far following the event byte.
+///
+/// Returns `None` if more data is needed to determine the length.
+pub type EventLength = fn(evt: u8, data: &[u8]) -> Option<usize>;
+
+/// An asynchronous event emitted by a resource.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub resource: u8,
+    pub evt: u8,
+    pub data: Vec<u8>,
+}
+
+pub(crate) struct EventShared {
+    ep: Endpoint<Bulk, In>,
+    buf: Vec<u8>,
+    lengths: [Option<EventLength>; 64],
+    queues: [VecDeque<Event>; 64],
+    /// Set after event data that could not be framed, to discard the rest of
+    /// the run of transfers it is part of.
+    unframed: bool,
+}
+
+impl EventShared {
+    pub(crate) fn new(ep: Endpoint<Bulk, In>) -> Self {
+        Self {
+            ep,
+            buf: Vec::new(),
+            lengths: [None; 64],
+            queues: std::array::from_fn(|_| VecDeque::new()),
+            unframed: false,
+        }
+    }
+
+    pub(crate) fn subscribe(&mut self, resource: u8, len: EventLength) {
+        self.lengths[resource as usize] = Some(len);
+        self.queues[resource as usize].clear();
+    }
+
+    pub(crate) fn try_next(&mut self, resource: u8) -> Option<Event> {
+        self.queues[resource as usize].pop_front()
+    }
+
+    pub(crate) async fn receive(&mut self) -> Result<(), RequestError> {
+        while self.ep.pending() < 2 {
+            self.ep.submit(Buffer::new(EVENT_TRANSFER_SIZE));
+        }
+
+        let res = self.ep.next_complete().await;
+        res.status.map_err(RequestError::Usb)?;
+        debug!("Event {:x?}", &res.buffer[..]);
+
+        // Events do not span short transfers, so framing restarts after one
+        let short = res.buffer.len() < res.buffer.requested_len();
+        if self.unframed {
+            warn!(
+                "Discarding {} bytes of unframed event data",
+                res.buffer.len()
+            );
+        } else {
+            self.buf.extend_from_slice(&res.buffer);
+            self.parse();
+        }
+
+        if short {
+            if !self.buf.is_empty() {
+                warn!("Discarding incomplete event data {:x?}", self.buf);
+                self.buf.clear();
+            }
+            self.unframed = false;
+        }
+
+        Ok(())
+    }
+
+    fn parse(&mut self) {
+        while let Some(&byte) = self.buf.first() {
+            let resource = byte & 0x3f;
+            let evt = byte >> 6;
+
+            // The length of the event is unknown, so the data following it
+            // can't be framed until the end of the transfer
+            let Some(len) = self.lengths[resource as usize] else {
+                warn!("Event for unsubscribed resource {resource}, discarding {:x?}", self.buf);
+                self.buf.clear();
+                self.unframed = true;
+                return;
+            };
+
+            let Some(len) = len(evt, &self.buf[1..]) else {
+                return;
+            };
+
+            if self.buf.len() < 1 + len {
+                return;
+            }
+
+            let data = self.buf[1..1 + len].to_vec();
+            self.buf.drain(..1 + len);
+            self.queues[resource as usize].push_back(Event {
+                resource,
+                evt,
+                data,
+            });
+        }
+    }
+}
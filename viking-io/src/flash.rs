@@ -0,0 +1,309 @@
+use std::sync::Arc;
+
+use crate::{
+    CommandQueue, RequestError, cmd_delay,
+    spi::{ChipSelect, Controller, Device, Lanes, Phases},
+};
+
+/// Maximum number of data bytes transferred by a single command.
+const CHUNK_LEN: usize = 256;
+
+/// Program page size of common serial NOR flash.
+pub const PAGE_SIZE: u32 = 256;
+
+pub mod opcode {
+    pub const WRITE_ENABLE: u8 = 0x06;
+    pub const READ_STATUS: u8 = 0x05;
+    pub const JEDEC_ID: u8 = 0x9F;
+    pub const FAST_READ: u8 = 0x0B;
+    pub const FAST_READ_DUAL_OUT: u8 = 0x3B;
+    pub const FAST_READ_QUAD_OUT: u8 = 0x6B;
+    pub const FAST_READ_4B: u8 = 0x0C;
+    pub const FAST_READ_DUAL_OUT_4B: u8 = 0x3C;
+    pub const FAST_READ_QUAD_OUT_4B: u8 = 0x6C;
+    pub const PAGE_PROGRAM: u8 = 0x02;
+    pub const PAGE_PROGRAM_QUAD_IN: u8 = 0x32;
+    pub const PAGE_PROGRAM_4B: u8 = 0x12;
+    pub const PAGE_PROGRAM_QUAD_IN_4B: u8 = 0x34;
+    pub const SECTOR_ERASE: u8 = 0x20;
+    pub const SECTOR_ERASE_4B: u8 = 0x21;
+    pub const BLOCK_ERASE: u8 = 0xD8;
+    pub const BLOCK_ERASE_4B: u8 = 0xDC;
+    pub const CHIP_ERASE: u8 = 0xC7;
+}
+
+/// Status register bit set while a program or erase operation is in progress.
+pub const STATUS_BUSY: u8 = 1 << 0;
+
+/// Delay in microseconds between polls of the status register.
+const POLL_INTERVAL_US: u16 = 1000;
+
+/// Serial NOR flash on a SPI controller, optionally using dual or quad data
+/// phases when the controller supports them.
+///
+/// Transfers use a single data line unless wider data phases are selected
+/// with [`read_lanes`](Flash::read_lanes) and
+/// [`write_lanes`](Flash::write_lanes).
+///
+/// All transfers use the controller's multi-lane command, which always shifts
+/// 8-bit words, so they are unaffected by the controller's word size.
+pub struct Flash<C = Arc<Controller>> {
+    device: Device<C>,
+    read_lanes: Lanes,
+    write_lanes: Lanes,
+    address_4byte: bool,
+}
+
+impl<C: AsRef<Controller>> Flash<C> {
+    /// Create a flash device selected by a GPIO output or hardware chip
+    /// select pin.
+    pub fn new(controller: C, chip_select: impl Into<ChipSelect>) -> Self {
+        Self::from_device(Device::new(controller, chip_select))
+    }
+
+    /// Create a flash device on a SPI device.
+    pub fn from_device(device: Device<C>) -> Self {
+        Self {
+            device,
+            read_lanes: Lanes::Single,
+            write_lanes: Lanes::Single,
+            address_4byte: false,
+        }
+    }
+
+    fn controller(&self) -> &Controller {
+        self.device.controller.as_ref()
+    }
+
+    /// Lanes used for the data phase of reads. Defaults to single.
+    ///
+    /// Quad reads require the flash's quad enable bit to be set, which is
+    /// vendor-specific and not handled here. Without it, quad reads return
+    /// invalid data.
+    pub fn read_lanes(mut self, lanes: Lanes) -> Self {
+        assert!(self.controller().supports_lanes(lanes));
+        self.read_lanes = lanes;
+        self
+    }
+
+    /// Lanes used for the data phase of page programs. Only single and quad
+    /// are supported by common flash. Defaults to single.
+    ///
+    /// Quad programs require the flash's quad enable bit to be set, which is
+    /// vendor-specific and not handled here. Without it, quad programs write
+    /// invalid data.
+    pub fn write_lanes(mut self, lanes: Lanes) -> Self {
+        assert!(lanes != Lanes::Dual && self.controller().supports_lanes(lanes));
+        self.write_lanes = lanes;
+        self
+    }
+
+    /// Use 4-byte address instructions, for devices larger than 16 MiB.
+    pub fn address_4byte(mut self) -> Self {
+        self.address_4byte = true;
+        self
+    }
+
+    /// Queue a transaction with chip select asserted, reading the data phase
+    /// into `buf`.
+    async fn push_read<'a>(
+        &'a self,
+        queue: &mut CommandQueue<'a>,
+        phases: Phases,
+        buf: &'a mut [u8],
+    ) {
+        let controller = self.controller();
+        queue.push(self.device.chip_select.cmd_assert()).await;
+        for (i, chunk) in buf.chunks_mut(CHUNK_LEN).enumerate() {
+            let phases = if i == 0 {
+                phases
+            } else {
+                Phases {
+                    data: phases.data,
+                    ..Default::default()
+                }
+            };
+            queue
+                .push_read(
+                    controller.cmd_multi_read(&phases, chunk.len() as u16),
+                    chunk,
+                )
+                .await;
+        }
+        queue.push(self.device.chip_select.cmd_release()).await;
+    }
+
+    /// Queue a transaction with chip select asserted, writing `data` in the
+    /// data phase.
+    async fn push_write<'a>(
+        &'a self,
+        queue: &mut CommandQueue<'a>,
+        phases: Phases,
+        data: &'a [u8],
+    ) {
+        let controller = self.controller();
+        queue.push(self.device.chip_select.cmd_assert()).await;
+        queue
+            .push(controller.cmd_multi_write(&phases, &data[..data.len().min(CHUNK_LEN)]))
+            .await;
+        for chunk in data.chunks(CHUNK_LEN).skip(1) {
+            let phases = Phases {
+                data: phases.data,
+                ..Default::default()
+            };
+            queue.push(controller.cmd_multi_write(&phases, chunk)).await;
+        }
+        queue.push(self.device.chip_select.cmd_release()).await;
+    }
+
+    fn instruction(opcode: u8) -> Phases {
+        Phases {
+            instruction: Some((Lanes::Single, opcode)),
+            ..Default::default()
+        }
+    }
+
+    fn addressed(&self, opcode: u8, addr: u32) -> Phases {
+        Phases {
+            instruction: Some((Lanes::Single, opcode)),
+            address: Some((Lanes::Single, addr)),
+            address_4byte: self.address_4byte,
+            ..Default::default()
+        }
+    }
+
+    /// Read the manufacturer and device ID.
+    pub async fn jedec_id(&self) -> Result<[u8; 3], RequestError> {
+        let mut id = [0; 3];
+        let mut queue = self.controller().resource.interface.queue();
+        self.push_read(&mut queue, Self::instruction(opcode::JEDEC_ID), &mut id)
+            .await;
+        queue.finish().await?;
+        Ok(id)
+    }
+
+    pub async fn read_status(&self) -> Result<u8, RequestError> {
+        let mut status = [0; 1];
+        let mut queue = self.controller().resource.interface.queue();
+        self.push_read(
+            &mut queue,
+            Self::instruction(opcode::READ_STATUS),
+            &mut status,
+        )
+        .await;
+        queue.finish().await?;
+        Ok(status[0])
+    }
+
+    /// Poll the status register until no program or erase is in progress.
+    ///
+    /// Polls after the first are delayed on the device, to avoid flooding
+    /// the USB link during long operations such as erases.
+    pub async fn wait_ready(&self) -> Result<(), RequestError> {
+        let mut delay = false;
+        loop {
+            let mut status = [0; 1];
+            let mut queue = self.controller().resource.interface.queue();
+            if delay {
+                queue.push(cmd_delay(POLL_INTERVAL_US)).await;
+            }
+            self.push_read(
+                &mut queue,
+                Self::instruction(opcode::READ_STATUS),
+                &mut status,
+            )
+            .await;
+            queue.finish().await?;
+
+            if status[0] & STATUS_BUSY == 0 {
+                return Ok(());
+            }
+            delay = true;
+        }
+    }
+
+    pub async fn read(&self, addr: u32, buf: &mut [u8]) -> Result<(), RequestError> {
+        let op = match (self.read_lanes, self.address_4byte) {
+            (Lanes::Single, false) => opcode::FAST_READ,
+            (Lanes::Dual, false) => opcode::FAST_READ_DUAL_OUT,
+            (Lanes::Quad, false) => opcode::FAST_READ_QUAD_OUT,
+            (Lanes::Single, true) => opcode::FAST_READ_4B,
+            (Lanes::Dual, true) => opcode::FAST_READ_DUAL_OUT_4B,
+            (Lanes::Quad, true) => opcode::FAST_READ_QUAD_OUT_4B,
+        };
+        let phases = Phases {
+            dummy_cycles: 8,
+            data: self.read_lanes,
+            ..self.addressed(op, addr)
+        };
+
+        let mut queue = self.controller().resource.interface.queue();
+        self.push_read(&mut queue, phases, buf).await;
+        queue.finish().await
+    }
+
+    /// Program `data` starting at `addr`, split at page boundaries, waiting
+    /// for each page to complete. The target area must be erased.
+    pub async fn program(&self, addr: u32, data: &[u8]) -> Result<(), RequestError> {
+        let op = match (self.write_lanes, self.address_4byte) {
+            (Lanes::Quad, false) => opcode::PAGE_PROGRAM_QUAD_IN,
+            (Lanes::Quad, true) => opcode::PAGE_PROGRAM_QUAD_IN_4B,
+            (_, false) => opcode::PAGE_PROGRAM,
+            (_, true) => opcode::PAGE_PROGRAM_4B,
+        };
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let page_addr = addr + offset as u32;
+            let len = ((PAGE_SIZE - page_addr % PAGE_SIZE) as usize).min(data.len() - offset);
+            let phases = Phases {
+                data: self.write_lanes,
+                ..self.addressed(op, page_addr)
+            };
+
+            let mut queue = self.controller().resource.interface.queue();
+            self.push_write(&mut queue, Self::instruction(opcode::WRITE_ENABLE), &[])
+                .await;
+            self.push_write(&mut queue, phases, &data[offset..offset + len])
+                .await;
+            queue.finish().await?;
+            self.wait_ready().await?;
+
+            offset += len;
+        }
+        Ok(())
+    }
+
+    async fn erase(&self, phases: Phases) -> Result<(), RequestError> {
+        let mut queue = self.controller().resource.interface.queue();
+        self.push_write(&mut queue, Self::instruction(opcode::WRITE_ENABLE), &[])
+            .await;
+        self.push_write(&mut queue, phases, &[]).await;
+        queue.finish().await?;
+        self.wait_ready().await
+    }
+
+    /// Erase the 4 KiB sector containing `addr`.
+    pub async fn erase_sector(&self, addr: u32) -> Result<(), RequestError> {
+        let op = if self.address_4byte {
+            opcode::SECTOR_ERASE_4B
+        } else {
+            opcode::SECTOR_ERASE
+        };
+        self.erase(self.addressed(op, addr)).await
+    }
+
+    /// Erase the 64 KiB block containing `addr`.
+    pub async fn erase_block(&self, addr: u32) -> Result<(), RequestError> {
+        let op = if self.address_4byte {
+            opcode::BLOCK_ERASE_4B
+        } else {
+            opcode::BLOCK_ERASE
+        };
+        self.erase(self.addressed(op, addr)).await
+    }
+
+    pub async fn erase_chip(&self) -> Result<(), RequestError> {
+        self.erase(Self::instruction(opcode::CHIP_ERASE)).await
+    }
+}
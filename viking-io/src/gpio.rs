@@ -1,15 +1,127 @@
+use futures_lite::{Stream, stream};
+use zerocopy::IntoBytes;
+
 use crate::{
-    RequestError, Resource,
-    command::{Command, StatusResponse},
+    Error, RequestError, Resource, ResourceMode,
+    command::{Command, ScalarResponse, StatusResponse},
     resource_mode,
 };
-use viking_protocol::protocol::gpio::pin as protocol;
+use viking_protocol::protocol::gpio::{bank, edge_interrupt, pin as protocol};
 
 pub struct Gpio {
     pub(crate) resource: Resource,
 }
 
-resource_mode!(Gpio, GpioBuilder, protocol::PROTOCOL);
+/// Internal pull resistor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pull {
+    #[default]
+    None,
+    Up,
+    Down,
+}
+
+pub struct GpioBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+}
+
+impl ResourceMode for Gpio {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = GpioBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        GpioBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+        }
+    }
+}
+
+impl GpioBuilder {
+    fn set_flag(&mut self, flag: protocol::ConfigFlags, value: bool) {
+        self.config.flags = if value {
+            self.config.flags.union(flag)
+        } else {
+            self.config.flags.difference(flag)
+        };
+    }
+
+    /// Enable an internal pull-up or pull-down resistor.
+    pub fn pull(mut self, pull: Pull) -> Self {
+        self.config.pull = match pull {
+            Pull::None => protocol::pull::NONE,
+            Pull::Up => protocol::pull::UP,
+            Pull::Down => protocol::pull::DOWN,
+        };
+        self
+    }
+
+    pub fn pull_up(self) -> Self {
+        self.pull(Pull::Up)
+    }
+
+    pub fn pull_down(self) -> Self {
+        self.pull(Pull::Down)
+    }
+
+    /// Drive strength level, from 1 (weakest) to the number of levels
+    /// supported by the pin, or 0 for the device default.
+    pub fn drive_strength(mut self, level: u8) -> Self {
+        self.config.drive = level;
+        self
+    }
+
+    /// Only drive the pin low; a high output leaves it floating.
+    pub fn open_drain(mut self, open_drain: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::OPEN_DRAIN, open_drain);
+        self
+    }
+
+    /// Limit the output slew rate to reduce ringing and EMI.
+    pub fn slow_slew(mut self, slow: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::SLOW_SLEW, slow);
+        self
+    }
+
+    pub async fn enable(self) -> Result<Gpio, Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        match self.config.pull {
+            protocol::pull::UP if !desc.flags.contains(protocol::ModeFlags::PULL_UP) => {
+                Err("pull-up not supported")?
+            }
+            protocol::pull::DOWN if !desc.flags.contains(protocol::ModeFlags::PULL_DOWN) => {
+                Err("pull-down not supported")?
+            }
+            _ => {}
+        }
+        if self.config.flags.contains(protocol::ConfigFlags::OPEN_DRAIN)
+            && !desc.flags.contains(protocol::ModeFlags::OPEN_DRAIN)
+        {
+            Err("open-drain not supported")?
+        }
+        if self.config.flags.contains(protocol::ConfigFlags::SLOW_SLEW)
+            && !desc.flags.contains(protocol::ModeFlags::SLEW_RATE)
+        {
+            Err("slew rate control not supported")?
+        }
+        if self.config.drive > desc.drive_levels {
+            Err("drive strength not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        Ok(Gpio { resource })
+    }
+}
 
 impl Gpio {
     pub fn id(&self) -> u8 {
@@ -58,3 +170,232 @@ impl Gpio {
         self.resource.interface.run(self.cmd_write(false)).await
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+pub struct EdgeInterrupt {
+    resource: Resource,
+    timestamp_clock: u32,
+}
+
+pub struct EdgeInterruptBuilder {
+    resource: Resource,
+    mode: u8,
+    config: edge_interrupt::Config,
+}
+
+impl ResourceMode for EdgeInterrupt {
+    const PROTOCOL: u16 = edge_interrupt::PROTOCOL;
+    type Builder = EdgeInterruptBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        EdgeInterruptBuilder {
+            resource,
+            mode,
+            config: edge_interrupt::Config::default(),
+        }
+    }
+}
+
+impl EdgeInterruptBuilder {
+    /// Emit events on rising edges only.
+    pub fn rising(mut self) -> Self {
+        self.config.flags = edge_interrupt::ConfigFlags::RISING;
+        self
+    }
+
+    /// Emit events on falling edges only.
+    pub fn falling(mut self) -> Self {
+        self.config.flags = edge_interrupt::ConfigFlags::FALLING;
+        self
+    }
+
+    /// Emit events on both rising and falling edges (default).
+    pub fn both(mut self) -> Self {
+        self.config.flags =
+            edge_interrupt::ConfigFlags::RISING.union(edge_interrupt::ConfigFlags::FALLING);
+        self
+    }
+
+    /// Ignore edges until the pin has been stable for `us` microseconds.
+    pub fn debounce_us(mut self, us: u16) -> Self {
+        self.config.debounce.set(us);
+        self
+    }
+
+    pub async fn enable(self) -> Result<EdgeInterrupt, Error> {
+        use edge_interrupt::{ConfigFlags, ModeFlags};
+
+        let desc: edge_interrupt::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let rising = self.config.flags.contains(ConfigFlags::RISING);
+        let falling = self.config.flags.contains(ConfigFlags::FALLING);
+        let supported = match (rising, falling) {
+            (true, true) => desc.flags.contains(ModeFlags::BOTH),
+            (true, false) => desc.flags.contains(ModeFlags::RISING),
+            (false, true) => desc.flags.contains(ModeFlags::FALLING),
+            (false, false) => false,
+        };
+        if !supported {
+            Err("edge selection not supported")?
+        }
+
+        let debounce = self.config.debounce.get();
+        if debounce != 0
+            && (!desc.flags.contains(ModeFlags::DEBOUNCE) || debounce > desc.max_debounce.get())
+        {
+            Err("debounce time not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        resource.subscribe_events(|_, _| Some(4)).await;
+
+        Ok(EdgeInterrupt {
+            resource,
+            timestamp_clock: desc.timestamp_clock.get(),
+        })
+    }
+}
+
+impl EdgeInterrupt {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Frequency in Hz of the counter used for event timestamps.
+    pub fn timestamp_clock(&self) -> u32 {
+        self.timestamp_clock
+    }
+
+    pub fn cmd_enable(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, edge_interrupt::cmd::ENABLE, (), ())
+    }
+
+    pub async fn enable(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_enable()).await
+    }
+
+    pub fn cmd_disable(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, edge_interrupt::cmd::DISABLE, (), ())
+    }
+
+    pub async fn disable(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_disable()).await
+    }
+
+    pub fn cmd_time(&self) -> Command<(), ScalarResponse<u32>> {
+        Command::new(
+            self.resource.id,
+            edge_interrupt::cmd::TIME,
+            (),
+            ScalarResponse::new(),
+        )
+    }
+
+    /// Read the current value of the timestamp counter.
+    pub async fn time(&self) -> Result<u32, RequestError> {
+        self.resource.interface.run(self.cmd_time()).await
+    }
+
+    /// Wait for the next edge and return it along with its timestamp.
+    ///
+    /// Returns an error if the device dropped events because they were not
+    /// collected quickly enough. Subsequent calls resume with the events
+    /// following the overflow.
+    pub async fn next_edge(&self) -> Result<(Edge, u32), RequestError> {
+        let event = self.resource.next_event().await?;
+        let timestamp = u32::from_le_bytes(event.data[..4].try_into().unwrap());
+        match event.evt {
+            edge_interrupt::evt::RISING => Ok((Edge::Rising, timestamp)),
+            edge_interrupt::evt::FALLING => Ok((Edge::Falling, timestamp)),
+            edge_interrupt::evt::OVERFLOW => {
+                Err(RequestError::Protocol("edge events lost to overflow"))
+            }
+            _ => Err(RequestError::Protocol("unknown edge interrupt event")),
+        }
+    }
+
+    /// Stream of edges and their timestamps.
+    pub fn edges(&self) -> impl Stream<Item = Result<(Edge, u32), RequestError>> + '_ {
+        stream::unfold(self, |this| async move { Some((this.next_edge().await, this)) })
+    }
+}
+
+/// Group of pins read and written together with a single command.
+pub struct Bank {
+    pub(crate) resource: Resource,
+}
+
+resource_mode!(Bank, BankBuilder, bank::PROTOCOL);
+
+impl Bank {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    fn mode_descriptor(&self) -> &[u8] {
+        let mode = self.resource.mode_id.expect("bank is configured");
+        self.resource.descriptor().mode(mode).unwrap().descriptor()
+    }
+
+    /// Number of bits in the bank's bitmaps.
+    pub fn width(&self) -> u8 {
+        self.mode_descriptor().first().copied().unwrap_or(0).min(bank::MAX_WIDTH)
+    }
+
+    /// Resource IDs of the member pins, indexed by bit position.
+    ///
+    /// An ID of 0 means the bit has no pin.
+    pub fn pins(&self) -> &[u8] {
+        let desc = self.mode_descriptor();
+        desc.get(1..).map_or(&[], |p| &p[..p.len().min(self.width() as usize)])
+    }
+
+    /// Bitmap of the bits that correspond to a pin.
+    pub fn mask(&self) -> u32 {
+        self.pins()
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| **id != 0)
+            .fold(0, |mask, (bit, _)| mask | 1 << bit)
+    }
+
+    pub fn cmd_read(&self) -> Command<(), ScalarResponse<u32>> {
+        Command::new(self.resource.id, bank::cmd::READ, (), ScalarResponse::new())
+    }
+
+    /// Read the levels of all pins in the bank.
+    pub async fn read(&self) -> Result<u32, RequestError> {
+        self.resource.interface.run(self.cmd_read()).await
+    }
+
+    pub fn cmd_write(&self, mask: u32, value: u32) -> Command<(u32, u32), ()> {
+        Command::new(self.resource.id, bank::cmd::WRITE, (mask, value), ())
+    }
+
+    /// Simultaneously set the output level of the pins selected by `mask` to
+    /// the corresponding bits of `value`.
+    pub async fn write(&self, mask: u32, value: u32) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_write(mask, value)).await
+    }
+
+    pub fn cmd_set_dir(&self, outputs: u32) -> Command<u32, ()> {
+        Command::new(self.resource.id, bank::cmd::SET_DIR, outputs, ())
+    }
+
+    /// Configure the pins whose bits are set in `outputs` as outputs, and the
+    /// remaining pins as inputs.
+    pub async fn set_dir(&self, outputs: u32) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_set_dir(outputs)).await
+    }
+}
@@ -1,15 +1,16 @@
 use crate::{
-    RequestError, Resource,
-    command::{Command, ScalarResponse, SliceResponse},
+    CommandQueue, RequestError, Resource, ResourceMode, ResponseHandle,
+    command::{Command, SliceResponse},
     resource_mode,
 };
-use embedded_hal_async::i2c::Operation;
+use embedded_hal_async::i2c::{Operation, SevenBitAddress};
 use nusb::transfer::TransferError;
 use thiserror::Error;
-use viking_protocol::protocol::i2c::{controller, scl, sda};
+use viking_protocol::protocol::i2c::{controller, scl, sda, target};
+use zerocopy::IntoBytes;
 
 pub struct Controller {
-    resource: Resource,
+    pub(crate) resource: Resource,
 }
 
 resource_mode!(Controller, ControllerBuilder, controller::PROTOCOL);
@@ -40,6 +41,98 @@ impl Controller {
     pub fn cmd_stop(&self) -> Command<(), ()> {
         Command::new(self.resource.id, controller::cmd::STOP, (), ())
     }
+
+    fn mode_flags(&self) -> controller::ModeFlags {
+        let mode = self.resource.mode_id.expect("controller is configured");
+        let desc: Option<controller::DescribeMode> = self.resource.mode_descriptor(mode);
+        desc.map_or(controller::ModeFlags::EMPTY, |d| d.flags)
+    }
+
+    /// Probe each non-reserved 7-bit address, returning the addresses that
+    /// acknowledged.
+    ///
+    /// Addresses are probed with an empty write if the controller supports
+    /// `ZERO_LEN_WRITE`, and otherwise with a one-byte read, which may have
+    /// side effects on some devices. As many probes are sent per batch as fit
+    /// the device's command and response buffers; when a NACK aborts the rest
+    /// of a batch, scanning resumes at the next address.
+    pub async fn scan(&self) -> Result<Vec<u8>, Error> {
+        use controller::ModeFlags;
+        use viking_protocol::errors;
+
+        let flags = self.mode_flags();
+        let zero_len = flags.contains(ModeFlags::ZERO_LEN_WRITE);
+        let addr_nack = flags.contains(ModeFlags::ADDR_NACK);
+
+        let mut found = Vec::new();
+        let mut next = SCAN_FIRST;
+        while next <= SCAN_LAST {
+            let mut batch = self.resource.interface.batch();
+            let mut probes = Vec::new();
+            for address in next..=SCAN_LAST {
+                let start = self.cmd_start(address << 1 | !zero_len as u8);
+                let stop = self.cmd_stop();
+                let read = self.cmd_read(1);
+                let write = self.cmd_write(&[]);
+
+                // Only start a probe if all of its commands fit
+                let mut len = add_len(start.encoded_len(), stop.encoded_len());
+                if !zero_len {
+                    len = add_len(len, read.encoded_len());
+                } else if !addr_nack {
+                    len = add_len(len, write.encoded_len());
+                }
+                if !batch.has_space(len) {
+                    break;
+                }
+
+                let start = batch.push(start);
+                let data = if !zero_len {
+                    Some(Probe::Read(batch.push(read)))
+                } else if !addr_nack {
+                    // NACK is only reported by the first data command
+                    Some(Probe::Write(batch.push(write)))
+                } else {
+                    None
+                };
+                batch.push(stop);
+                probes.push((address, start, data));
+            }
+            next += probes.len() as u8;
+
+            let res = batch.run().await?;
+            for (i, (address, start, data)) in probes.into_iter().enumerate() {
+                let status = res.get(start).and_then(|()| match data {
+                    Some(Probe::Read(h)) => res.get(h).map(|_| ()),
+                    Some(Probe::Write(h)) => res.get(h),
+                    None => Ok(()),
+                });
+                match status {
+                    Ok(()) => found.push(address),
+                    Err(RequestError::Status(errors::ERR_ADDR_NACK)) => {}
+                    Err(RequestError::PriorError) if i > 0 => {
+                        next = address;
+                        break;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Lowest and highest non-reserved 7-bit addresses.
+const SCAN_FIRST: u8 = 0x08;
+const SCAN_LAST: u8 = 0x77;
+
+fn add_len(a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+enum Probe {
+    Read(ResponseHandle<SliceResponse>),
+    Write(ResponseHandle<()>),
 }
 
 #[derive(Debug, Error)]
@@ -107,46 +200,458 @@ impl embedded_hal_async::i2c::ErrorType for Controller {
     type Error = Error;
 }
 
-impl embedded_hal_async::i2c::I2c for Controller {
-    async fn transaction(
-        &mut self,
-        address: u8,
-        operations: &mut [Operation<'_>],
-    ) -> Result<(), Self::Error> {
-        let mut queue = self.resource.interface.queue();
+/// Address of the device in a transaction.
+#[derive(Clone, Copy)]
+pub(crate) enum Address {
+    Seven(u8),
+    Ten(u16),
+}
+
+/// Command of a planned transaction.
+enum Step<'a> {
+    Start(u8),
+    Write(&'a [u8]),
+    Read(&'a mut [u8]),
+    Stop,
+}
+
+impl Controller {
+    /// Translate `operations` into controller commands, checking that the
+    /// sequence of starts is supported.
+    fn plan<'a>(
+        &self,
+        address: Address,
+        operations: &'a mut [Operation<'_>],
+        ten_bit_low: &'a [u8; 1],
+    ) -> Result<Vec<Step<'a>>, Error> {
+        use controller::ModeFlags;
+
+        let flags = self.mode_flags();
+        if matches!(address, Address::Ten(_)) && !flags.contains(ModeFlags::TEN_BIT) {
+            return Err(Error::Unsupported);
+        }
 
-        let mut last_dir = None;
+        let mut steps = Vec::new();
+        // Direction of each addressed segment, and the number of bytes in the
+        // current one
+        let mut segments: Vec<bool> = Vec::new();
+        let mut len = 0;
+        // A 7-bit write without data needs ZERO_LEN_WRITE, while a 10-bit
+        // write always includes the low address byte
+        let zero_len_ok =
+            flags.contains(ModeFlags::ZERO_LEN_WRITE) || matches!(address, Address::Ten(_));
 
         for op in operations {
+            let read = matches!(op, Operation::Read(_));
+            if segments.last() != Some(&read) {
+                if segments.last() == Some(&false) && len == 0 && !zero_len_ok {
+                    return Err(Error::Unsupported);
+                }
+                match address {
+                    Address::Seven(a) => steps.push(Step::Start(a << 1 | read as u8)),
+                    Address::Ten(a) => {
+                        // The low address byte is written after the header of a
+                        // write, and a read restarts with only the header
+                        let header = 0xF0 | (a >> 7) as u8 & 0x06;
+                        if read && segments.is_empty() {
+                            steps.push(Step::Start(header));
+                            steps.push(Step::Write(ten_bit_low));
+                            segments.push(false);
+                        }
+                        steps.push(Step::Start(header | read as u8));
+                        if !read {
+                            steps.push(Step::Write(ten_bit_low));
+                        }
+                    }
+                }
+                segments.push(read);
+                len = 0;
+            }
+
             match op {
                 Operation::Read(r) => {
-                    if last_dir != Some(true) {
-                        queue.push(self.cmd_start(address << 1 | 1)).await;
-                        last_dir = Some(true);
-                    }
-                    for chunk in r.chunks_mut(255) {
-                        queue
-                            .push_read(self.cmd_read(chunk.len() as u8), chunk)
-                            .await;
-                    }
+                    len += r.len();
+                    steps.extend(r.chunks_mut(255).map(Step::Read));
                 }
                 Operation::Write(w) => {
-                    if last_dir != Some(false) {
-                        queue.push(self.cmd_start(address << 1)).await;
-                        last_dir = Some(false);
-                    }
-                    for chunk in w.chunks(255) {
-                        queue.push(self.cmd_write(chunk)).await;
-                    }
+                    len += w.len();
+                    steps.extend(w.chunks(255).map(Step::Write));
                 }
             }
         }
 
-        if last_dir.is_some() {
-            queue.push(self.cmd_stop()).await;
+        if segments.is_empty() {
+            return Ok(steps);
+        }
+
+        if segments.last() == Some(&false) && len == 0 && !zero_len_ok {
+            return Err(Error::Unsupported);
+        }
+
+        let restart = flags.contains(ModeFlags::REPEATED_START)
+            || flags.contains(ModeFlags::REPEATED_START_SAME_ADDRESS)
+            || (segments == [false, true] && flags.contains(ModeFlags::WRITE_THEN_READ));
+        if segments.len() > 1 && !restart {
+            return Err(Error::Unsupported);
+        }
+
+        steps.push(Step::Stop);
+        Ok(steps)
+    }
+
+    /// Run a transaction with a device at a 10-bit address, as
+    /// [`embedded_hal_async::i2c::I2c::transaction`] does for 7-bit addresses.
+    ///
+    /// Requires the `TEN_BIT` capability flag, and a repeated start if the
+    /// transaction begins with a read.
+    pub async fn transaction_10bit(
+        &mut self,
+        address: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if address > 0x3ff {
+            return Err(Error::Protocol("invalid 10-bit address"));
+        }
+        self.run_transaction(Address::Ten(address), operations)
+            .await
+    }
+
+    pub(crate) async fn run_transaction(
+        &self,
+        address: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.run_transactions(address, &mut [operations]).await
+    }
+
+    /// Run several transactions with the same device in a single command
+    /// queue, checking each against the controller's capabilities.
+    ///
+    /// Without `SPLIT`, each transaction must fit in a batch of its own, and
+    /// the queue is flushed between transactions as needed.
+    pub(crate) async fn run_transactions(
+        &self,
+        address: Address,
+        transactions: &mut [&mut [Operation<'_>]],
+    ) -> Result<(), Error> {
+        let ten_bit_low = match address {
+            Address::Seven(_) => [0],
+            Address::Ten(a) => [a as u8],
+        };
+        let mut planned = Vec::new();
+        for operations in transactions.iter_mut() {
+            let steps = self.plan(address, operations, &ten_bit_low)?;
+            if !steps.is_empty() {
+                planned.push(steps);
+            }
+        }
+        if planned.is_empty() {
+            return Ok(());
+        }
+
+        let split = self.mode_flags().contains(controller::ModeFlags::SPLIT);
+        let mut queue = self.resource.interface.queue();
+        for steps in planned {
+            if !split {
+                let len = steps.iter().fold((0, 0), |(c, r), step| {
+                    let (step_c, step_r) = self.step_len(step);
+                    (c + step_c, r + step_r)
+                });
+                if !queue.reserve(len).await {
+                    return Err(Error::Unsupported);
+                }
+            }
+            self.push_steps(&mut queue, steps).await;
+        }
+        Ok(queue.finish().await?)
+    }
+
+    /// Request and response lengths of the command for `step`.
+    fn step_len(&self, step: &Step) -> (usize, usize) {
+        match step {
+            Step::Start(a) => self.cmd_start(*a).encoded_len(),
+            Step::Write(w) => self.cmd_write(w).encoded_len(),
+            Step::Read(r) => self.cmd_read(r.len() as u8).encoded_len(),
+            Step::Stop => self.cmd_stop().encoded_len(),
+        }
+    }
+
+    async fn push_steps<'a>(&self, queue: &mut CommandQueue<'a>, steps: Vec<Step<'a>>) {
+        for step in steps {
+            match step {
+                Step::Start(a) => queue.push(self.cmd_start(a)).await,
+                Step::Write(w) => queue.push(self.cmd_write(w)).await,
+                Step::Read(r) => queue.push_read(self.cmd_read(r.len() as u8), r).await,
+                Step::Stop => queue.push(self.cmd_stop()).await,
+            }
+        }
+    }
+
+    /// Write `command` to a 7-bit address, then read a length byte followed
+    /// by that many bytes, up to `max`, and `extra` trailing bytes, as in an
+    /// SMBus block read. Returns the bytes read, starting with the length.
+    ///
+    /// With `SPLIT`, the length byte is read in a batch of its own so that
+    /// only the reported number of bytes is clocked out of the device.
+    /// Otherwise the whole transaction is sent before the length is known,
+    /// and `max` bytes are always read.
+    pub(crate) async fn read_counted(
+        &self,
+        address: u8,
+        command: &[u8],
+        max: usize,
+        extra: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; 1 + max + extra];
+        if !self.mode_flags().contains(controller::ModeFlags::SPLIT) {
+            let mut ops = vec![Operation::Write(command), Operation::Read(&mut buf)];
+            if command.is_empty() {
+                ops.remove(0);
+            }
+            self.run_transaction(Address::Seven(address), &mut ops)
+                .await?;
+            return Ok(buf);
+        }
+
+        {
+            let mut ops = vec![Operation::Write(command), Operation::Read(&mut buf[..1])];
+            if command.is_empty() {
+                ops.remove(0);
+            }
+            let mut steps = self.plan(Address::Seven(address), &mut ops, &[0])?;
+            // Leave the transaction open to read the data
+            steps.pop();
+            let mut queue = self.resource.interface.queue();
+            self.push_steps(&mut queue, steps).await;
             queue.finish().await?;
         }
-        Ok(())
+
+        // On an invalid length, end the transaction and let the caller report
+        // the length
+        let len = buf[0] as usize;
+        let len = if len > max { 0 } else { len + extra };
+        buf.truncate(1 + len);
+
+        let mut queue = self.resource.interface.queue();
+        for chunk in buf[1..].chunks_mut(255) {
+            queue
+                .push_read(self.cmd_read(chunk.len() as u8), chunk)
+                .await;
+        }
+        queue.push(self.cmd_stop()).await;
+        queue.finish().await?;
+        Ok(buf)
+    }
+}
+
+impl embedded_hal_async::i2c::I2c<SevenBitAddress> for Controller {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.run_transaction(Address::Seven(address), operations)
+            .await
+    }
+}
+
+/// I2C target (peripheral) that answers at a configured address.
+///
+/// In register mode, the device emulates a register-based peripheral from a
+/// register file loaded by the host: the first byte written in a transaction
+/// sets the register pointer, and subsequent writes and reads access the
+/// register file with auto-increment.
+///
+/// In forward mode, each transaction is reported to the host as
+/// [`TargetEvent`]s, and the device stretches the clock on reads until the
+/// host supplies the data with [`Target::respond`].
+pub struct Target {
+    resource: Resource,
+    registers: u16,
+}
+
+pub struct TargetBuilder {
+    resource: Resource,
+    mode: u8,
+    config: target::Config,
+}
+
+/// Activity on the bus addressed to a [`Target`] in forward mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetEvent {
+    /// The controller wrote bytes.
+    Write(Vec<u8>),
+
+    /// The controller is reading and waiting for [`Target::respond`].
+    Read,
+
+    /// The controller ended the transaction.
+    Stop,
+}
+
+impl ResourceMode for Target {
+    const PROTOCOL: u16 = target::PROTOCOL;
+    type Builder = TargetBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        TargetBuilder {
+            resource,
+            mode,
+            config: target::Config {
+                flags: target::ConfigFlags::EMPTY,
+                address: 0.into(),
+            },
+        }
+    }
+}
+
+impl TargetBuilder {
+    /// Respond to a 7-bit address.
+    pub fn address(mut self, address: u8) -> Self {
+        self.config.flags = self.config.flags.difference(target::ConfigFlags::TEN_BIT);
+        self.config.address.set(address as u16);
+        self
+    }
+
+    /// Respond to a 10-bit address.
+    pub fn address_10bit(mut self, address: u16) -> Self {
+        self.config.flags = self.config.flags.union(target::ConfigFlags::TEN_BIT);
+        self.config.address.set(address);
+        self
+    }
+
+    /// Answer from the register file loaded by the host (default).
+    pub fn registers(mut self) -> Self {
+        self.config.flags = self.config.flags.difference(target::ConfigFlags::FORWARD);
+        self
+    }
+
+    /// Forward transactions to the host as events.
+    pub fn forward(mut self) -> Self {
+        self.config.flags = self.config.flags.union(target::ConfigFlags::FORWARD);
+        self
+    }
+
+    pub async fn enable(self) -> Result<Target, crate::Error> {
+        use target::{ConfigFlags, ModeFlags};
+
+        let desc: target::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let forward = self.config.flags.contains(ConfigFlags::FORWARD);
+        if forward && !desc.flags.contains(ModeFlags::FORWARD) {
+            Err("forward mode not supported")?
+        }
+        if !forward && !desc.flags.contains(ModeFlags::REGISTERS) {
+            Err("register mode not supported")?
+        }
+
+        let address = self.config.address.get();
+        if self.config.flags.contains(ConfigFlags::TEN_BIT) {
+            if !desc.flags.contains(ModeFlags::TEN_BIT) {
+                Err("10-bit addressing not supported")?
+            }
+            if address > 0x3ff {
+                Err("invalid 10-bit address")?
+            }
+        } else if !(0x08..=0x77).contains(&address) {
+            Err("invalid or reserved 7-bit address")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        if forward {
+            resource
+                .subscribe_events(|evt, data| match evt {
+                    target::evt::WRITE => Some(1 + *data.first()? as usize),
+                    _ => Some(0),
+                })
+                .await;
+        }
+
+        Ok(Target {
+            resource,
+            registers: desc.registers.get(),
+        })
+    }
+}
+
+impl Target {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Size of the register file in bytes.
+    pub fn registers(&self) -> u16 {
+        self.registers
+    }
+
+    pub fn cmd_load<'a>(&self, offset: u16, data: &'a [u8]) -> Command<(u16, &'a [u8]), ()> {
+        Command::new(self.resource.id, target::cmd::LOAD, (offset, data), ())
+    }
+
+    pub fn cmd_read(&self, offset: u16, len: u8) -> Command<(u16, u8), SliceResponse> {
+        Command::new(
+            self.resource.id,
+            target::cmd::READ,
+            (offset, len),
+            SliceResponse::new(len as usize),
+        )
+    }
+
+    pub fn cmd_respond<'a>(&self, data: &'a [u8]) -> Command<&'a [u8], ()> {
+        Command::new(self.resource.id, target::cmd::RESPOND, data, ())
+    }
+
+    /// Write `data` to the register file starting at `offset`.
+    pub async fn load(&self, offset: u16, data: &[u8]) -> Result<(), Error> {
+        let mut queue = self.resource.interface.queue();
+        for (i, chunk) in data.chunks(255).enumerate() {
+            let offset = offset.wrapping_add((i * 255) as u16);
+            queue.push(self.cmd_load(offset, chunk)).await;
+        }
+        Ok(queue.finish().await?)
+    }
+
+    /// Read the register file starting at `offset`, e.g. to check values
+    /// written by the controller.
+    pub async fn read(&self, offset: u16, buf: &mut [u8]) -> Result<(), Error> {
+        let mut queue = self.resource.interface.queue();
+        for (i, chunk) in buf.chunks_mut(255).enumerate() {
+            let offset = offset.wrapping_add((i * 255) as u16);
+            queue
+                .push_read(self.cmd_read(offset, chunk.len() as u8), chunk)
+                .await;
+        }
+        Ok(queue.finish().await?)
+    }
+
+    /// Wait for the next activity on the bus in forward mode.
+    pub async fn next_event(&self) -> Result<TargetEvent, Error> {
+        let event = self.resource.next_event().await?;
+        match event.evt {
+            target::evt::WRITE => Ok(TargetEvent::Write(event.data[1..].to_vec())),
+            target::evt::READ => Ok(TargetEvent::Read),
+            target::evt::STOP => Ok(TargetEvent::Stop),
+            _ => Err(Error::Protocol("unknown i2c target event")),
+        }
+    }
+
+    /// Supply data for a pending read in forward mode.
+    ///
+    /// If the controller reads more bytes than supplied, another
+    /// [`TargetEvent::Read`] is emitted.
+    pub async fn respond(&self, data: &[u8]) -> Result<(), Error> {
+        let mut queue = self.resource.interface.queue();
+        for chunk in data.chunks(255) {
+            queue.push(self.cmd_respond(chunk)).await;
+        }
+        Ok(queue.finish().await?)
     }
 }
 
@@ -0,0 +1,338 @@
+use std::{future::Future, pin::Pin};
+
+use futures_lite::future;
+use zerocopy::IntoBytes;
+
+use crate::{RequestError, Resource, ResourceMode, command::Command};
+use viking_protocol::protocol::i2s as protocol;
+
+/// Maximum number of bytes written by a single command.
+const MAX_CHUNK: usize = 255;
+
+/// I2S audio interface.
+///
+/// Samples are exchanged as interleaved frames of `channels` signed samples,
+/// right-aligned in an `i32` regardless of the word size.
+pub struct I2s {
+    resource: Resource,
+    word_size: u8,
+    channels: u8,
+    sample_rate: u32,
+    chunk_len: usize,
+}
+
+pub struct I2sBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+}
+
+impl ResourceMode for I2s {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = I2sBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        I2sBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+        }
+    }
+}
+
+impl I2sBuilder {
+    fn set_flag(&mut self, flag: protocol::ConfigFlags, value: bool) {
+        self.config.flags = if value {
+            self.config.flags.union(flag)
+        } else {
+            self.config.flags.difference(flag)
+        };
+    }
+
+    /// Sample rate in Hz. Defaults to 48 kHz.
+    pub fn sample_rate(mut self, hz: u32) -> Self {
+        self.config.sample_rate.set(hz);
+        self
+    }
+
+    /// Bits per sample: 16, 24 or 32. Defaults to 16.
+    pub fn word_size(mut self, bits: u8) -> Self {
+        self.config.word_size = bits;
+        self
+    }
+
+    /// Number of channels per frame. Defaults to 2.
+    pub fn channels(mut self, channels: u8) -> Self {
+        self.config.channels = channels;
+        self
+    }
+
+    /// Enable transmitting (default).
+    pub fn tx(mut self, enable: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::TX, enable);
+        self
+    }
+
+    /// Enable receiving.
+    pub fn rx(mut self, enable: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::RX, enable);
+        self
+    }
+
+    /// Follow an external bit clock and word select instead of generating
+    /// them.
+    pub fn target(mut self) -> Self {
+        self.set_flag(protocol::ConfigFlags::TARGET, true);
+        self
+    }
+
+    pub async fn enable(self) -> Result<I2s, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let flags = self.config.flags;
+        let supported = |c: protocol::ConfigFlags, m: protocol::ModeFlags| {
+            !flags.contains(c) || desc.flags.contains(m)
+        };
+        if !supported(protocol::ConfigFlags::TX, protocol::ModeFlags::TX)
+            || !supported(protocol::ConfigFlags::RX, protocol::ModeFlags::RX)
+            || !supported(protocol::ConfigFlags::TARGET, protocol::ModeFlags::TARGET)
+        {
+            Err("direction or clock mode not supported")?
+        }
+
+        let word_size = match self.config.word_size {
+            16 => protocol::WordSizes::BITS_16,
+            24 => protocol::WordSizes::BITS_24,
+            32 => protocol::WordSizes::BITS_32,
+            _ => Err("word size must be 16, 24 or 32")?,
+        };
+        if !desc.word_sizes.contains(word_size) {
+            Err("word size not supported")?
+        }
+
+        if self.config.channels == 0 || self.config.channels > desc.max_channels {
+            Err("channel count not supported")?
+        }
+
+        let rate = self.config.sample_rate.get();
+        if rate < desc.min_sample_rate.get() || rate > desc.max_sample_rate.get() {
+            Err("sample rate not supported")?
+        }
+
+        // Write half of the device's buffer at a time, so that one half
+        // plays while the other is filled
+        let frame_len =
+            protocol::sample_bytes(self.config.word_size) * self.config.channels as usize;
+        if frame_len > MAX_CHUNK {
+            Err("frame does not fit in a single write")?
+        }
+        let chunk_len = (desc.tx_buffer_len.get() as usize / 2).min(MAX_CHUNK);
+        let chunk_len = (chunk_len / frame_len).max(1) * frame_len;
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        if flags.contains(protocol::ConfigFlags::RX) {
+            resource
+                .subscribe_events(|evt, data| match evt {
+                    protocol::evt::DATA => Some(1 + *data.first()? as usize),
+                    _ => Some(0),
+                })
+                .await;
+        }
+
+        Ok(I2s {
+            resource,
+            word_size: self.config.word_size,
+            channels: self.config.channels,
+            sample_rate: rate,
+            chunk_len,
+        })
+    }
+}
+
+impl I2s {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    pub fn word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    fn sample_bytes(&self) -> usize {
+        protocol::sample_bytes(self.word_size)
+    }
+
+    pub fn cmd_start(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, protocol::cmd::START, (), ())
+    }
+
+    pub fn cmd_stop(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, protocol::cmd::STOP, (), ())
+    }
+
+    pub fn cmd_write<'a>(&self, data: &'a [u8]) -> Command<&'a [u8], ()> {
+        Command::new(self.resource.id, protocol::cmd::WRITE, data, ())
+    }
+
+    /// Start clocking frames, transmitting from the buffer and emitting
+    /// received data.
+    pub async fn start(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_start()).await
+    }
+
+    pub async fn stop(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_stop()).await
+    }
+
+    fn encode(&self, samples: &[i32]) -> Vec<u8> {
+        if self.sample_bytes() == 2 {
+            samples
+                .iter()
+                .flat_map(|&s| (s as i16).to_le_bytes())
+                .collect()
+        } else {
+            samples.iter().flat_map(|&s| s.to_le_bytes()).collect()
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<i32> {
+        if self.sample_bytes() == 2 {
+            data.chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as i32)
+                .collect()
+        } else {
+            data.chunks_exact(4)
+                .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+                .collect()
+        }
+    }
+
+    /// Write interleaved samples to the transmit buffer, waiting for space as
+    /// necessary.
+    pub async fn write(&self, samples: &[i32]) -> Result<(), RequestError> {
+        let data = self.encode(samples);
+        let mut queue = self.resource.interface.queue();
+        for chunk in data.chunks(self.chunk_len) {
+            queue.push(self.cmd_write(chunk)).await;
+        }
+        queue.finish().await
+    }
+
+    /// Create a writer that buffers samples and sends them in chunks of half
+    /// the device's transmit buffer.
+    pub fn writer(&self) -> Writer<'_> {
+        Writer {
+            i2s: self,
+            buf: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// Wait for the next block of received interleaved samples.
+    ///
+    /// Returns an error if the device's buffer overflowed and data was lost.
+    pub async fn read(&self) -> Result<Vec<i32>, RequestError> {
+        let event = self.resource.next_event().await?;
+        match event.evt {
+            protocol::evt::DATA => Ok(self.decode(&event.data[1..])),
+            protocol::evt::OVERFLOW => Err(RequestError::Protocol("i2s receive overflow")),
+            _ => Err(RequestError::Protocol("unknown i2s event")),
+        }
+    }
+}
+
+/// Write of a chunk that has been submitted to the device.
+type PendingWrite<'a> = Pin<Box<dyn Future<Output = Result<(), RequestError>> + Send + 'a>>;
+
+/// Double-buffered transmit stream.
+///
+/// Samples are accumulated on the host and sent in chunks of half the
+/// device's transmit buffer. The write of the last chunk is left outstanding
+/// while the caller generates the next one, and is only waited for before the
+/// following chunk is sent, so one half of the device's buffer plays while the
+/// other is filled.
+///
+/// Call [`flush`](Writer::flush) before dropping the writer, or the
+/// outstanding chunk and any buffered samples may be lost.
+pub struct Writer<'a> {
+    i2s: &'a I2s,
+    buf: Vec<u8>,
+    pending: Option<PendingWrite<'a>>,
+}
+
+impl<'a> Writer<'a> {
+    /// Wait for the outstanding chunk to be accepted by the device.
+    async fn wait(&mut self) -> Result<(), RequestError> {
+        match self.pending.take() {
+            Some(pending) => pending.await,
+            None => Ok(()),
+        }
+    }
+
+    /// Send the first `len` bytes of the buffer after the outstanding chunk
+    /// is accepted, leaving the new write outstanding.
+    ///
+    /// The bytes are removed from the buffer once sent, even if the write
+    /// fails, so they are never sent twice.
+    async fn send(&mut self, len: usize) -> Result<(), RequestError> {
+        self.wait().await?;
+
+        let i2s: &'a I2s = self.i2s;
+        let mut batch = i2s.resource.interface.batch();
+        let cmd = i2s.cmd_write(&self.buf[..len]);
+        if !batch.can_fit(&cmd) {
+            return Err(RequestError::Protocol(
+                "i2s chunk exceeds the device's batch size",
+            ));
+        }
+        let h = batch.push(cmd);
+        self.buf.drain(..len);
+
+        // Poll once to submit the batch, then let it complete in the
+        // background of the next call
+        let mut pending = Box::pin(async move { batch.run().await?.get(h) });
+        match future::poll_once(&mut pending).await {
+            Some(res) => res,
+            None => {
+                self.pending = Some(pending);
+                Ok(())
+            }
+        }
+    }
+
+    /// Append interleaved samples, sending complete chunks.
+    ///
+    /// Returns once every complete chunk except the last has been accepted by
+    /// the device.
+    pub async fn write(&mut self, samples: &[i32]) -> Result<(), RequestError> {
+        self.buf.extend(self.i2s.encode(samples));
+        let chunk_len = self.i2s.chunk_len;
+        while self.buf.len() >= chunk_len {
+            self.send(chunk_len).await?;
+        }
+        Ok(())
+    }
+
+    /// Send any remaining buffered samples and wait for all writes to be
+    /// accepted.
+    pub async fn flush(&mut self) -> Result<(), RequestError> {
+        if !self.buf.is_empty() {
+            self.send(self.buf.len()).await?;
+        }
+        self.wait().await
+    }
+}
@@ -0,0 +1,381 @@
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, Raw, SliceResponse},
+};
+use viking_protocol::protocol::jtag as protocol;
+
+/// Maximum number of bits transferred by a single command.
+const MAX_BITS: usize = 248;
+
+/// Maximum number of devices detected by [`Jtag::scan_idcodes`].
+const MAX_DEVICES: usize = 32;
+
+/// State of the TAP controller state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TapState {
+    TestLogicReset,
+    RunTestIdle,
+    SelectDrScan,
+    CaptureDr,
+    ShiftDr,
+    Exit1Dr,
+    PauseDr,
+    Exit2Dr,
+    UpdateDr,
+    SelectIrScan,
+    CaptureIr,
+    ShiftIr,
+    Exit1Ir,
+    PauseIr,
+    Exit2Ir,
+    UpdateIr,
+}
+
+impl TapState {
+    /// State entered on the next TCK rising edge with the given TMS level.
+    pub fn next(self, tms: bool) -> TapState {
+        use TapState::*;
+        match (self, tms) {
+            (TestLogicReset, false) => RunTestIdle,
+            (TestLogicReset, true) => TestLogicReset,
+            (RunTestIdle, false) => RunTestIdle,
+            (RunTestIdle, true) => SelectDrScan,
+            (SelectDrScan, false) => CaptureDr,
+            (SelectDrScan, true) => SelectIrScan,
+            (CaptureDr, false) => ShiftDr,
+            (CaptureDr, true) => Exit1Dr,
+            (ShiftDr, false) => ShiftDr,
+            (ShiftDr, true) => Exit1Dr,
+            (Exit1Dr, false) => PauseDr,
+            (Exit1Dr, true) => UpdateDr,
+            (PauseDr, false) => PauseDr,
+            (PauseDr, true) => Exit2Dr,
+            (Exit2Dr, false) => ShiftDr,
+            (Exit2Dr, true) => UpdateDr,
+            (UpdateDr, false) => RunTestIdle,
+            (UpdateDr, true) => SelectDrScan,
+            (SelectIrScan, false) => CaptureIr,
+            (SelectIrScan, true) => TestLogicReset,
+            (CaptureIr, false) => ShiftIr,
+            (CaptureIr, true) => Exit1Ir,
+            (ShiftIr, false) => ShiftIr,
+            (ShiftIr, true) => Exit1Ir,
+            (Exit1Ir, false) => PauseIr,
+            (Exit1Ir, true) => UpdateIr,
+            (PauseIr, false) => PauseIr,
+            (PauseIr, true) => Exit2Ir,
+            (Exit2Ir, false) => ShiftIr,
+            (Exit2Ir, true) => UpdateIr,
+            (UpdateIr, false) => RunTestIdle,
+            (UpdateIr, true) => SelectDrScan,
+        }
+    }
+
+    /// Shortest TMS sequence leading from this state to `target`.
+    pub fn path_to(self, target: TapState) -> Vec<bool> {
+        let mut paths = vec![(self, Vec::new())];
+        loop {
+            if let Some((_, path)) = paths.iter().find(|(s, _)| *s == target) {
+                return path.clone();
+            }
+            paths = paths
+                .into_iter()
+                .flat_map(|(s, path)| {
+                    [false, true].map(|tms| {
+                        let mut path = path.clone();
+                        path.push(tms);
+                        (s.next(tms), path)
+                    })
+                })
+                .collect();
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid scan chain: TDO stuck or chain too long")]
+    InvalidChain,
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+/// JTAG TAP controller.
+///
+/// Tracks the state of the TAP controllers on the chain, so that operations
+/// only clock the TMS transitions needed from the current state.
+pub struct Jtag {
+    resource: Resource,
+    state: TapState,
+}
+
+pub struct JtagBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+    frequency: Option<u32>,
+}
+
+impl ResourceMode for Jtag {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = JtagBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        JtagBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+            frequency: None,
+        }
+    }
+}
+
+impl JtagBuilder {
+    /// Maximum TCK frequency in Hz. Defaults to the fastest supported.
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.frequency = Some(hz);
+        self
+    }
+
+    /// Enable the controller and reset the TAP to Run-Test/Idle.
+    pub async fn enable(mut self) -> Result<Jtag, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let base_clock = desc.base_clock.get();
+        let div = match self.frequency {
+            Some(hz) if hz > 0 => base_clock.div_ceil(hz).max(1),
+            _ => 1,
+        };
+        if div > desc.max_div.get() {
+            Err("frequency too low")?
+        }
+        self.config.clock_div.set(div);
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        let mut jtag = Jtag {
+            resource,
+            state: TapState::TestLogicReset,
+        };
+        jtag.reset()
+            .await
+            .map_err(|e| crate::Error::new("TAP reset failed", e))?;
+        Ok(jtag)
+    }
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0; bits.len().div_ceil(8)];
+    for (i, &b) in bits.iter().enumerate() {
+        bytes[i / 8] |= (b as u8) << (i % 8);
+    }
+    bytes
+}
+
+impl Jtag {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Current state of the TAP controller.
+    pub fn state(&self) -> TapState {
+        self.state
+    }
+
+    /// Command clocking `bits` bits of `tms`, LSB first, with TDI low.
+    pub fn cmd_tms<'a>(&self, bits: u8, tms: &'a [u8]) -> Command<(u8, Raw<'a>), ()> {
+        assert_eq!(tms.len(), (bits as usize).div_ceil(8));
+        Command::new(self.resource.id, protocol::cmd::TMS, (bits, Raw(tms)), ())
+    }
+
+    /// Command shifting `bits` bits of `tdi` and capturing TDO, LSB first.
+    pub fn cmd_shift<'a>(
+        &self,
+        exit: bool,
+        bits: u8,
+        tdi: &'a [u8],
+    ) -> Command<(u8, u8, Raw<'a>), SliceResponse> {
+        assert_eq!(tdi.len(), (bits as usize).div_ceil(8));
+        let flags = if exit { protocol::shift::EXIT } else { 0 };
+        Command::new(
+            self.resource.id,
+            protocol::cmd::SHIFT,
+            (flags, bits, Raw(tdi)),
+            SliceResponse::new(tdi.len()),
+        )
+    }
+
+    /// Command shifting `bits` bits of `tdi`, LSB first, without capturing TDO.
+    pub fn cmd_shift_out<'a>(
+        &self,
+        exit: bool,
+        bits: u8,
+        tdi: &'a [u8],
+    ) -> Command<(u8, u8, Raw<'a>), ()> {
+        assert_eq!(tdi.len(), (bits as usize).div_ceil(8));
+        let flags = if exit { protocol::shift::EXIT } else { 0 };
+        Command::new(
+            self.resource.id,
+            protocol::cmd::SHIFT_OUT,
+            (flags, bits, Raw(tdi)),
+            (),
+        )
+    }
+
+    async fn clock_tms(&mut self, tms: &[bool]) -> Result<(), RequestError> {
+        let mut queue = self.resource.interface.queue();
+        for chunk in tms.chunks(MAX_BITS) {
+            let bytes = pack_bits(chunk);
+            queue.push(self.cmd_tms(chunk.len() as u8, &bytes)).await;
+        }
+        queue.finish().await?;
+        self.state = tms.iter().fold(self.state, |s, &b| s.next(b));
+        Ok(())
+    }
+
+    /// Move all TAPs to Test-Logic-Reset and then to Run-Test/Idle.
+    pub async fn reset(&mut self) -> Result<(), RequestError> {
+        self.state = TapState::TestLogicReset;
+        self.clock_tms(&[true, true, true, true, true, false]).await
+    }
+
+    /// Move to `state` using the shortest TMS sequence.
+    pub async fn goto(&mut self, state: TapState) -> Result<(), RequestError> {
+        let path = self.state.path_to(state);
+        if path.is_empty() {
+            return Ok(());
+        }
+        self.clock_tms(&path).await
+    }
+
+    /// Clock `cycles` cycles in Run-Test/Idle.
+    pub async fn idle(&mut self, cycles: usize) -> Result<(), RequestError> {
+        self.goto(TapState::RunTestIdle).await?;
+        self.clock_tms(&vec![false; cycles]).await
+    }
+
+    /// Shift `bits` bits through the current shift state, capturing TDO into
+    /// `tdo` if provided, and leave to the Exit1 state. With no bits, nothing
+    /// is clocked and the TAP stays in the shift state.
+    async fn shift(
+        &mut self,
+        bits: usize,
+        tdi: &[u8],
+        tdo: Option<&mut [u8]>,
+    ) -> Result<(), RequestError> {
+        if bits == 0 {
+            return Ok(());
+        }
+        assert!(tdi.len() * 8 >= bits);
+        let chunks = bits.div_ceil(MAX_BITS);
+
+        // Re-align the input so that each chunk starts on a byte boundary
+        let chunk_bytes = MAX_BITS / 8;
+        let tdi_chunks: Vec<&[u8]> = (0..chunks)
+            .map(|i| {
+                let n = (bits - i * MAX_BITS).min(MAX_BITS);
+                &tdi[i * chunk_bytes..i * chunk_bytes + n.div_ceil(8)]
+            })
+            .collect();
+
+        let mut queue = self.resource.interface.queue();
+        match tdo {
+            Some(tdo) => {
+                assert!(tdo.len() * 8 >= bits);
+                let dests = tdo[..bits.div_ceil(8)].chunks_mut(chunk_bytes);
+                for (i, (src, dest)) in tdi_chunks.iter().zip(dests).enumerate() {
+                    let n = (bits - i * MAX_BITS).min(MAX_BITS) as u8;
+                    let cmd = self.cmd_shift(i == chunks - 1, n, src);
+                    queue.push_read(cmd, dest).await;
+                }
+            }
+            None => {
+                for (i, src) in tdi_chunks.iter().enumerate() {
+                    let n = (bits - i * MAX_BITS).min(MAX_BITS) as u8;
+                    queue
+                        .push(self.cmd_shift_out(i == chunks - 1, n, src))
+                        .await;
+                }
+            }
+        }
+        queue.finish().await?;
+
+        self.state = self.state.next(true);
+        Ok(())
+    }
+
+    /// Shift `bits` bits of `tdi` into the instruction register, returning
+    /// the captured TDO bits, and return to Run-Test/Idle.
+    pub async fn shift_ir(&mut self, bits: usize, tdi: &[u8]) -> Result<Vec<u8>, RequestError> {
+        let mut tdo = vec![0; bits.div_ceil(8)];
+        self.goto(TapState::ShiftIr).await?;
+        self.shift(bits, tdi, Some(&mut tdo)).await?;
+        self.goto(TapState::RunTestIdle).await?;
+        Ok(tdo)
+    }
+
+    /// Shift `bits` bits of `tdi` into the selected data register, returning
+    /// the captured TDO bits, and return to Run-Test/Idle.
+    pub async fn shift_dr(&mut self, bits: usize, tdi: &[u8]) -> Result<Vec<u8>, RequestError> {
+        let mut tdo = vec![0; bits.div_ceil(8)];
+        self.goto(TapState::ShiftDr).await?;
+        self.shift(bits, tdi, Some(&mut tdo)).await?;
+        self.goto(TapState::RunTestIdle).await?;
+        Ok(tdo)
+    }
+
+    /// Shift `bits` bits of `tdi` into the instruction register without
+    /// capturing TDO.
+    pub async fn write_ir(&mut self, bits: usize, tdi: &[u8]) -> Result<(), RequestError> {
+        self.goto(TapState::ShiftIr).await?;
+        self.shift(bits, tdi, None).await?;
+        self.goto(TapState::RunTestIdle).await
+    }
+
+    /// Shift `bits` bits of `tdi` into the selected data register without
+    /// capturing TDO.
+    pub async fn write_dr(&mut self, bits: usize, tdi: &[u8]) -> Result<(), RequestError> {
+        self.goto(TapState::ShiftDr).await?;
+        self.shift(bits, tdi, None).await?;
+        self.goto(TapState::RunTestIdle).await
+    }
+
+    /// Reset the chain and read the IDCODE of each device, starting with the
+    /// device closest to TDO.
+    ///
+    /// Devices without an IDCODE register select BYPASS after reset and are
+    /// reported as `None`.
+    pub async fn scan_idcodes(&mut self) -> Result<Vec<Option<u32>>, Error> {
+        let bits = (MAX_DEVICES + 1) * 32;
+        self.reset().await?;
+        let tdo = self.shift_dr(bits, &vec![0xff; bits / 8]).await?;
+        let bit = |i: usize| tdo[i / 8] >> (i % 8) & 1 != 0;
+
+        let mut devices = Vec::new();
+        let mut pos = 0;
+        while pos + 32 <= bits && devices.len() <= MAX_DEVICES {
+            if bit(pos) {
+                let idcode = (0..32).fold(0u32, |v, i| v | (bit(pos + i) as u32) << i);
+                if idcode == u32::MAX {
+                    return Ok(devices);
+                }
+                devices.push(Some(idcode));
+                pos += 32;
+            } else {
+                devices.push(None);
+                pos += 1;
+            }
+        }
+        Err(Error::InvalidChain)
+    }
+}
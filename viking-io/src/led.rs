@@ -1,5 +1,12 @@
-use crate::{RequestError, Resource, command::Command, resource_mode};
-use viking_protocol::protocol::led::binary as protocol;
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    CommandQueue, RequestError, Resource, ResourceMode,
+    command::{Command, Raw},
+    resource_mode,
+};
+use viking_protocol::protocol::led::{binary as protocol, dimmable, rgb, strip};
 
 pub struct Led {
     pub(crate) resource: Resource,
@@ -33,3 +40,464 @@ impl Led {
         self.resource.interface.run(self.cmd_set(false)).await
     }
 }
+
+/// LED with adjustable brightness.
+pub struct Dimmable {
+    resource: Resource,
+    desc: dimmable::DescribeMode,
+}
+
+pub struct DimmableBuilder {
+    resource: Resource,
+    mode: u8,
+}
+
+impl ResourceMode for Dimmable {
+    const PROTOCOL: u16 = dimmable::PROTOCOL;
+    type Builder = DimmableBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        DimmableBuilder { resource, mode }
+    }
+}
+
+impl DimmableBuilder {
+    pub async fn enable(self) -> Result<Dimmable, crate::Error> {
+        let desc: dimmable::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+        if !(1..=16).contains(&desc.bits) {
+            Err("invalid brightness bit depth")?
+        }
+
+        let mut resource = self.resource;
+        resource.configure(self.mode, &[]).await?;
+        Ok(Dimmable { resource, desc })
+    }
+}
+
+impl Dimmable {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// See [`color`](viking_protocol::protocol::led::binary::color).
+    pub fn color(&self) -> u8 {
+        self.desc.color
+    }
+
+    /// Number of significant bits in the brightness level.
+    pub fn bits(&self) -> u8 {
+        self.desc.bits
+    }
+
+    /// Brightness level at full intensity.
+    pub fn max_brightness(&self) -> u16 {
+        (u32::MAX >> (32 - self.desc.bits)) as u16
+    }
+
+    /// Levels above [`max_brightness`](Self::max_brightness) are clamped.
+    pub fn cmd_set_brightness(&self, level: u16) -> Command<u16, ()> {
+        let level = level.min(self.max_brightness());
+        Command::new(self.resource.id, dimmable::cmd::SET, level, ())
+    }
+
+    pub fn cmd_off(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, dimmable::cmd::OFF, (), ())
+    }
+
+    /// Set the brightness level, from 0 (off) to
+    /// [`max_brightness`](Self::max_brightness).
+    pub async fn set_brightness(&self, level: u16) -> Result<(), RequestError> {
+        let cmd = self.cmd_set_brightness(level);
+        self.resource.interface.run(cmd).await
+    }
+
+    pub async fn on(&self) -> Result<(), RequestError> {
+        self.set_brightness(self.max_brightness()).await
+    }
+
+    pub async fn off(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_off()).await
+    }
+}
+
+/// LED with independently adjustable red, green and blue channels.
+pub struct RgbLed {
+    resource: Resource,
+    desc: rgb::DescribeMode,
+}
+
+pub struct RgbLedBuilder {
+    resource: Resource,
+    mode: u8,
+}
+
+impl ResourceMode for RgbLed {
+    const PROTOCOL: u16 = rgb::PROTOCOL;
+    type Builder = RgbLedBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        RgbLedBuilder { resource, mode }
+    }
+}
+
+impl RgbLedBuilder {
+    pub async fn enable(self) -> Result<RgbLed, crate::Error> {
+        let desc: rgb::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+        if !(1..=16).contains(&desc.bits) {
+            Err("invalid intensity bit depth")?
+        }
+
+        let mut resource = self.resource;
+        resource.configure(self.mode, &[]).await?;
+        Ok(RgbLed { resource, desc })
+    }
+}
+
+impl RgbLed {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Number of significant bits in each channel's intensity.
+    pub fn bits(&self) -> u8 {
+        self.desc.bits
+    }
+
+    /// Channel intensity at full brightness.
+    pub fn max_intensity(&self) -> u16 {
+        (u32::MAX >> (32 - self.desc.bits)) as u16
+    }
+
+    /// Intensities above [`max_intensity`](Self::max_intensity) are clamped.
+    pub fn cmd_set_rgb(&self, r: u16, g: u16, b: u16) -> Command<(u16, u16, u16), ()> {
+        let max = self.max_intensity();
+        Command::new(
+            self.resource.id,
+            rgb::cmd::SET,
+            (r.min(max), g.min(max), b.min(max)),
+            (),
+        )
+    }
+
+    pub fn cmd_off(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, rgb::cmd::OFF, (), ())
+    }
+
+    /// Set the intensity of each channel, from 0 (off) to
+    /// [`max_intensity`](Self::max_intensity).
+    pub async fn set_rgb(&self, r: u16, g: u16, b: u16) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_set_rgb(r, g, b)).await
+    }
+
+    /// Set the color from 8-bit channel values, scaled to the LED's bit depth.
+    pub async fn set_color(&self, color: Rgb) -> Result<(), RequestError> {
+        let scale = |c: u8| ((c as u32 * self.max_intensity() as u32 + 127) / 255) as u16;
+        self.set_rgb(scale(color.r), scale(color.g), scale(color.b))
+            .await
+    }
+
+    pub async fn off(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_off()).await
+    }
+}
+
+/// Maximum number of pixels written by a single command.
+const CHUNK_PIXELS: usize = 64;
+
+/// Color of a pixel with red, green and blue channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// Color of a pixel with red, green, blue and white channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rgbw {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub w: u8,
+}
+
+impl Rgbw {
+    pub const fn new(r: u8, g: u8, b: u8, w: u8) -> Self {
+        Self { r, g, b, w }
+    }
+}
+
+impl From<Rgb> for Rgbw {
+    fn from(c: Rgb) -> Self {
+        Rgbw::new(c.r, c.g, c.b, 0)
+    }
+}
+
+/// LED strip timing variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    Ws2812,
+    Ws2811,
+    Sk6812,
+}
+
+impl Timing {
+    fn value(self) -> u8 {
+        match self {
+            Timing::Ws2812 => strip::timing::WS2812,
+            Timing::Ws2811 => strip::timing::WS2811,
+            Timing::Sk6812 => strip::timing::SK6812,
+        }
+    }
+
+    fn flag(self) -> strip::Timings {
+        match self {
+            Timing::Ws2812 => strip::Timings::WS2812,
+            Timing::Ws2811 => strip::Timings::WS2811,
+            Timing::Sk6812 => strip::Timings::SK6812,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StripError {
+    #[error("{len} pixels at offset {offset} extend past the end of the strip of {pixels}")]
+    OutOfRange {
+        offset: u16,
+        len: usize,
+        pixels: u16,
+    },
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+/// Addressable (WS2812-style) LED strip.
+///
+/// Pixels are written to a frame buffer on the device and transmitted to the
+/// strip by [`show`](Strip::show).
+pub struct Strip {
+    resource: Resource,
+    desc: strip::DescribeMode,
+    pixels: u16,
+}
+
+pub struct StripBuilder {
+    resource: Resource,
+    mode: u8,
+    config: strip::Config,
+}
+
+impl ResourceMode for Strip {
+    const PROTOCOL: u16 = strip::PROTOCOL;
+    type Builder = StripBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        StripBuilder {
+            resource,
+            mode,
+            config: strip::Config::default(),
+        }
+    }
+}
+
+impl StripBuilder {
+    /// Number of pixels on the strip. Defaults to the maximum supported by the
+    /// device.
+    pub fn pixels(mut self, pixels: u16) -> Self {
+        self.config.pixels.set(pixels);
+        self
+    }
+
+    /// Timing variant. Defaults to WS2812.
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.config.timing = timing.value();
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<Strip, crate::Error> {
+        let desc: strip::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let timing = match self.config.timing {
+            strip::timing::WS2811 => Timing::Ws2811,
+            strip::timing::SK6812 => Timing::Sk6812,
+            _ => Timing::Ws2812,
+        };
+        if !desc.timings.contains(timing.flag()) {
+            Err("timing not supported")?
+        }
+
+        if self.config.pixels.get() == 0 {
+            self.config.pixels = desc.max_pixels;
+        }
+        if self.config.pixels.get() > desc.max_pixels.get() {
+            Err("pixel count exceeds maximum")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        Ok(Strip {
+            resource,
+            desc,
+            pixels: self.config.pixels.get(),
+        })
+    }
+}
+
+impl Strip {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Number of pixels on the strip.
+    pub fn len(&self) -> u16 {
+        self.pixels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels == 0
+    }
+
+    /// Whether the pixels have a white channel.
+    pub fn has_white(&self) -> bool {
+        self.desc.flags.contains(strip::ModeFlags::WHITE)
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        if self.has_white() { 4 } else { 3 }
+    }
+
+    /// Encode a pixel in the strip's wire order. On strips without a white
+    /// channel, white is mixed into the color channels.
+    fn encode(&self, c: Rgbw, out: &mut Vec<u8>) {
+        let (r, g, b) = if self.has_white() {
+            (c.r, c.g, c.b)
+        } else {
+            (
+                c.r.saturating_add(c.w),
+                c.g.saturating_add(c.w),
+                c.b.saturating_add(c.w),
+            )
+        };
+        out.extend_from_slice(&match self.desc.color_order {
+            strip::order::RBG => [r, b, g],
+            strip::order::GRB => [g, r, b],
+            strip::order::GBR => [g, b, r],
+            strip::order::BRG => [b, r, g],
+            strip::order::BGR => [b, g, r],
+            _ => [r, g, b],
+        });
+        if self.has_white() {
+            out.push(c.w);
+        }
+    }
+
+    /// Command writing pixel data, already encoded in the strip's wire order,
+    /// to the frame buffer starting at pixel `offset`.
+    pub fn cmd_write_pixels<'a>(
+        &self,
+        offset: u16,
+        data: &'a [u8],
+    ) -> Command<(u16, u8, Raw<'a>), ()> {
+        let count = data.len() / self.bytes_per_pixel();
+        assert!(count <= u8::MAX as usize && count * self.bytes_per_pixel() == data.len());
+        Command::new(
+            self.resource.id,
+            strip::cmd::WRITE_PIXELS,
+            (offset, count as u8, Raw(data)),
+            (),
+        )
+    }
+
+    pub fn cmd_show(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, strip::cmd::SHOW, (), ())
+    }
+
+    /// Write pixels to the frame buffer starting at pixel `offset` without
+    /// updating the strip.
+    pub async fn write<P: Into<Rgbw> + Copy>(
+        &self,
+        offset: u16,
+        pixels: &[P],
+    ) -> Result<(), StripError> {
+        self.check_range(offset, pixels.len())?;
+        let data = self.encode_all(pixels);
+        let mut queue = self.resource.interface.queue();
+        self.push_pixels(&mut queue, offset, &data).await;
+        Ok(queue.finish().await?)
+    }
+
+    /// Transmit the frame buffer to the strip.
+    pub async fn show(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_show()).await
+    }
+
+    /// Write a full frame starting at the first pixel and show it.
+    pub async fn set_frame<P: Into<Rgbw> + Copy>(&self, pixels: &[P]) -> Result<(), StripError> {
+        self.check_range(0, pixels.len())?;
+        Ok(self.send_frame(pixels).await?)
+    }
+
+    /// Set all pixels to the same color and show it.
+    pub async fn fill(&self, color: impl Into<Rgbw>) -> Result<(), RequestError> {
+        let color = color.into();
+        self.send_frame(&vec![color; self.pixels as usize]).await
+    }
+
+    /// Turn off all pixels.
+    pub async fn clear(&self) -> Result<(), RequestError> {
+        self.fill(Rgbw::default()).await
+    }
+
+    fn encode_all<P: Into<Rgbw> + Copy>(&self, pixels: &[P]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(pixels.len() * self.bytes_per_pixel());
+        for &p in pixels {
+            self.encode(p.into(), &mut data);
+        }
+        data
+    }
+
+    async fn send_frame<P: Into<Rgbw> + Copy>(&self, pixels: &[P]) -> Result<(), RequestError> {
+        let data = self.encode_all(pixels);
+        let mut queue = self.resource.interface.queue();
+        self.push_pixels(&mut queue, 0, &data).await;
+        queue.push(self.cmd_show()).await;
+        queue.finish().await
+    }
+
+    fn check_range(&self, offset: u16, len: usize) -> Result<(), StripError> {
+        if offset as usize + len > self.pixels as usize {
+            return Err(StripError::OutOfRange {
+                offset,
+                len,
+                pixels: self.pixels,
+            });
+        }
+        Ok(())
+    }
+
+    async fn push_pixels<'a>(&self, queue: &mut CommandQueue<'a>, offset: u16, data: &'a [u8]) {
+        let chunk_len = CHUNK_PIXELS * self.bytes_per_pixel();
+        for (i, chunk) in data.chunks(chunk_len).enumerate() {
+            let offset = offset + (i * CHUNK_PIXELS) as u16;
+            queue.push(self.cmd_write_pixels(offset, chunk)).await;
+        }
+    }
+}
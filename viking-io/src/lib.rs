@@ -16,18 +16,32 @@ use nusb::{
     },
 };
 use thiserror::Error;
+use zerocopy::{FromBytes, IntoBytes};
 
+pub mod can;
 pub mod command;
 pub mod descriptor;
+pub mod event;
+pub mod flash;
 
 pub mod gpio;
 pub mod i2c;
+pub mod i2s;
+pub mod jtag;
 pub mod led;
+pub mod onewire;
+pub mod pio;
+pub mod regblock;
+pub mod regmap;
+pub mod smbus;
 pub mod spi;
+pub mod swd;
+pub mod timer;
 mod device;
 
 pub use device::{list_devices, DeviceMatcher, FoundDevice};
 use self::command::{Command, PayloadPattern, ResponsePattern, StaticResponsePattern};
+use self::event::{Event, EventLength, EventShared};
 
 #[derive(Debug)]
 pub struct Error {
@@ -65,7 +79,7 @@ impl std::error::Error for Error {
 pub struct Interface {
     intf: nusb::Interface,
     cmd_eps: async_lock::Mutex<CmdShared>,
-    ep_evt: Endpoint<Bulk, In>,
+    events: async_lock::Mutex<EventShared>,
     resources_used: AtomicU64,
     descriptor: descriptor::Resources,
     max_command_len: usize,
@@ -172,9 +186,9 @@ impl Interface {
                 ep_req,
                 ep_res,
             }),
-            ep_evt,
-            max_command_len: descriptor.max_cmd_len().min(320).max(65536) as usize,
-            max_response_len: descriptor.max_cmd_len().min(320).max(65536) as usize,
+            events: async_lock::Mutex::new(EventShared::new(ep_evt)),
+            max_command_len: descriptor.max_cmd_len().min(65536) as usize,
+            max_response_len: descriptor.max_res_len().min(65536) as usize,
             descriptor,
             resources_used: AtomicU64::new(0),
         });
@@ -211,8 +225,7 @@ impl Interface {
     async fn configure_resource(&self, resource: u8, mode: u8, data: &[u8]) -> Result<(), Error> {
         log::info!("configure resource {resource} as {mode}: {data:x?}");
         let intf_num = self.intf.interface_number();
-        Ok(self
-            .intf
+        self.intf
             .control_out(
                 ControlOut {
                     control_type: ControlType::Vendor,
@@ -226,7 +239,23 @@ impl Interface {
             )
             .await
             .map(drop)
-            .map_err(|e| Error::new("configure mode failed", e))?)
+            .map_err(|e| Error::new("configure mode failed", e))
+    }
+
+    async fn subscribe_events(&self, resource: u8, len: EventLength) {
+        self.events.lock().await.subscribe(resource, len);
+    }
+
+    async fn next_event(&self, resource: u8) -> Result<Event, RequestError> {
+        loop {
+            // Release the lock between transfers so that tasks waiting on
+            // events for other resources can collect them.
+            let mut events = self.events.lock().await;
+            if let Some(event) = events.try_next(resource) {
+                return Ok(event);
+            }
+            events.receive().await?;
+        }
     }
 
     pub fn batch(self: &Arc<Self>) -> CommandBatch<'_> {
@@ -284,8 +313,14 @@ impl<'a> CommandBatch<'a> {
     }
 
     pub fn can_fit<P: PayloadPattern, R: ResponsePattern>(&mut self, cmd: &Command<P, R>) -> bool {
-        self.req.len() + 1 + cmd.payload.len() <= self.intf.max_command_len
-            && self.response_len + 1 + cmd.response.len() <= self.intf.max_response_len
+        self.has_space(cmd.encoded_len())
+    }
+
+    /// Check whether commands with the total request and response lengths
+    /// given by `(command_len, response_len)` can be added to the batch.
+    pub(crate) fn has_space(&self, (command_len, response_len): (usize, usize)) -> bool {
+        self.req.len() + command_len <= self.intf.max_command_len
+            && self.response_len + response_len <= self.intf.max_response_len
     }
 
     pub fn push<P: PayloadPattern, R: ResponsePattern>(
@@ -317,7 +352,7 @@ impl<'a> CommandBatch<'a> {
             debug!("Ignored stale IN transfer");
         }
 
-        let zlp = self.req.len() % lock.ep_req.max_packet_size() == 0;
+        let zlp = self.req.len().is_multiple_of(lock.ep_req.max_packet_size());
         debug!("Send batch {:x?}", self.req);
         lock.ep_req.submit(Buffer::from(self.req));
         if zlp {
@@ -471,6 +506,17 @@ impl<'a> CommandQueue<'a> {
         self.responses.push((h.offset, &mut buf[..len]));
     }
 
+    /// Flush the queued commands if commands with the total request and
+    /// response lengths given by `len` would not fit in the current batch,
+    /// so that they are sent in the same batch. Returns `false` if they do
+    /// not fit even in an empty batch.
+    pub(crate) async fn reserve(&mut self, len: (usize, usize)) -> bool {
+        if !self.batch.has_space(len) && !self.responses.is_empty() {
+            self.flush().await;
+        }
+        self.batch.has_space(len)
+    }
+
     pub async fn finish(mut self) -> Result<(), RequestError> {
         self.flush().await;
         self.error
@@ -507,6 +553,18 @@ impl Resource {
         self.interface.descriptor.resource(self.id).unwrap()
     }
 
+    /// Parse the protocol-specific descriptor of a mode of this resource.
+    ///
+    /// Fields beyond the end of a shorter descriptor are zero.
+    pub fn mode_descriptor<T: FromBytes + IntoBytes>(&self, mode: u8) -> Option<T> {
+        let bytes = self.descriptor().mode(mode)?.descriptor();
+        let mut desc = T::new_zeroed();
+        let dest = desc.as_mut_bytes();
+        let len = bytes.len().min(dest.len());
+        dest[..len].copy_from_slice(&bytes[..len]);
+        Some(desc)
+    }
+
     pub async fn configure(&mut self, mode: u8, config: &[u8]) -> Result<(), Error> {
         self.interface
             .configure_resource(self.id, mode, config)
@@ -522,6 +580,17 @@ impl Resource {
         Ok(())
     }
 
+    /// Start collecting events for this resource, using `len` to determine
+    /// the length of each event's data.
+    pub async fn subscribe_events(&self, len: EventLength) {
+        self.interface.subscribe_events(self.id, len).await
+    }
+
+    /// Wait for the next event from this resource.
+    pub async fn next_event(&self) -> Result<Event, RequestError> {
+        self.interface.next_event(self.id).await
+    }
+
     pub fn as_mode<M: ResourceMode>(self) -> Result<M::Builder, Error> {
         let mode = self
             .descriptor()
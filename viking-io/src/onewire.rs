@@ -0,0 +1,352 @@
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, Raw, SliceResponse, StatusResponse},
+};
+use viking_protocol::protocol::onewire as protocol;
+
+/// Maximum number of bits transferred by a single command.
+const MAX_BITS: usize = 248;
+
+pub mod rom_cmd {
+    pub const READ_ROM: u8 = 0x33;
+    pub const MATCH_ROM: u8 = 0x55;
+    pub const SKIP_ROM: u8 = 0xCC;
+    pub const SEARCH_ROM: u8 = 0xF0;
+    pub const ALARM_SEARCH: u8 = 0xEC;
+}
+
+/// Dallas/Maxim CRC-8 (polynomial x^8 + x^5 + x^4 + 1) used for ROM codes and
+/// scratchpad contents.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |mut crc, &byte| {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 1;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+        crc
+    })
+}
+
+/// 64-bit ROM code identifying a device on the bus, in bus order: family code,
+/// 48-bit serial number, CRC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Rom(pub [u8; 8]);
+
+impl Rom {
+    pub fn family(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub fn serial(&self) -> u64 {
+        let mut serial = [0; 8];
+        serial[..6].copy_from_slice(&self.0[1..7]);
+        u64::from_le_bytes(serial)
+    }
+
+    pub fn crc_valid(&self) -> bool {
+        crc8(&self.0) == 0
+    }
+}
+
+impl std::fmt::Display for Rom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0.iter().rev() {
+            write!(f, "{b:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no presence pulse")]
+    NoPresence,
+
+    #[error("CRC mismatch")]
+    Crc,
+
+    #[error("search found inconsistent responses")]
+    Search,
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+/// 1-Wire bus controller.
+pub struct OneWire {
+    resource: Resource,
+    strong_pullup: bool,
+}
+
+pub struct OneWireBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+}
+
+impl ResourceMode for OneWire {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = OneWireBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        OneWireBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+        }
+    }
+}
+
+impl OneWireBuilder {
+    /// Use overdrive speed timing.
+    pub fn overdrive(mut self) -> Self {
+        self.config.speed = protocol::speed::OVERDRIVE;
+        self
+    }
+
+    pub async fn enable(self) -> Result<OneWire, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        if self.config.speed == protocol::speed::OVERDRIVE
+            && !desc.flags.contains(protocol::ModeFlags::OVERDRIVE)
+        {
+            Err("overdrive not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        Ok(OneWire {
+            resource,
+            strong_pullup: desc.flags.contains(protocol::ModeFlags::STRONG_PULLUP),
+        })
+    }
+}
+
+impl OneWire {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Whether the device can drive a strong pull-up for parasite-powered devices.
+    pub fn supports_strong_pullup(&self) -> bool {
+        self.strong_pullup
+    }
+
+    pub fn cmd_reset(&self) -> Command<(), StatusResponse> {
+        Command::new(self.resource.id, protocol::cmd::RESET, (), StatusResponse)
+    }
+
+    pub fn cmd_write_bits<'a>(&self, bits: u8, data: &'a [u8]) -> Command<(u8, Raw<'a>), ()> {
+        assert_eq!(data.len(), (bits as usize).div_ceil(8));
+        Command::new(
+            self.resource.id,
+            protocol::cmd::WRITE_BITS,
+            (bits, Raw(data)),
+            (),
+        )
+    }
+
+    pub fn cmd_read_bits(&self, bits: u8) -> Command<u8, SliceResponse> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::READ_BITS,
+            bits,
+            SliceResponse::new((bits as usize).div_ceil(8)),
+        )
+    }
+
+    pub fn cmd_strong_pullup(&self, ms: u16) -> Command<u16, ()> {
+        Command::new(self.resource.id, protocol::cmd::STRONG_PULLUP, ms, ())
+    }
+
+    /// Send a reset pulse, returning whether any device responded with a
+    /// presence pulse.
+    pub async fn reset(&self) -> Result<bool, RequestError> {
+        Ok(self.resource.interface.run(self.cmd_reset()).await? != 0)
+    }
+
+    /// Write `bits` bits from `data`, least significant bit of each byte first.
+    pub async fn write_bits(&self, bits: usize, data: &[u8]) -> Result<(), RequestError> {
+        assert!(data.len() * 8 >= bits);
+        let mut queue = self.resource.interface.queue();
+        for (i, chunk) in data[..bits.div_ceil(8)].chunks(MAX_BITS / 8).enumerate() {
+            let chunk_bits = (bits - i * MAX_BITS).min(MAX_BITS) as u8;
+            queue.push(self.cmd_write_bits(chunk_bits, chunk)).await;
+        }
+        queue.finish().await
+    }
+
+    /// Read `bits` bits into `data`, least significant bit of each byte first.
+    pub async fn read_bits(&self, bits: usize, data: &mut [u8]) -> Result<(), RequestError> {
+        assert!(data.len() * 8 >= bits);
+        let mut queue = self.resource.interface.queue();
+        for (i, chunk) in data[..bits.div_ceil(8)]
+            .chunks_mut(MAX_BITS / 8)
+            .enumerate()
+        {
+            let chunk_bits = (bits - i * MAX_BITS).min(MAX_BITS) as u8;
+            queue.push_read(self.cmd_read_bits(chunk_bits), chunk).await;
+        }
+        queue.finish().await
+    }
+
+    pub async fn write_bytes(&self, data: &[u8]) -> Result<(), RequestError> {
+        self.write_bits(data.len() * 8, data).await
+    }
+
+    pub async fn read_bytes(&self, data: &mut [u8]) -> Result<(), RequestError> {
+        self.read_bits(data.len() * 8, data).await
+    }
+
+    /// Write `data`, then drive the bus high for `ms` milliseconds to power
+    /// parasite-powered devices during an operation such as a temperature
+    /// conversion.
+    pub async fn write_bytes_pullup(&self, data: &[u8], ms: u16) -> Result<(), RequestError> {
+        let mut queue = self.resource.interface.queue();
+        for chunk in data.chunks(MAX_BITS / 8) {
+            queue
+                .push(self.cmd_write_bits((chunk.len() * 8) as u8, chunk))
+                .await;
+        }
+        queue.push(self.cmd_strong_pullup(ms)).await;
+        queue.finish().await
+    }
+
+    /// Reset the bus and address all devices.
+    pub async fn skip_rom(&self) -> Result<(), Error> {
+        let mut batch = self.resource.interface.batch();
+        let reset = batch.push(self.cmd_reset());
+        let write = batch.push(self.cmd_write_bits(8, &[rom_cmd::SKIP_ROM]));
+        let res = batch.run().await?;
+        if res.get(reset)? == 0 {
+            return Err(Error::NoPresence);
+        }
+        res.get(write)?;
+        Ok(())
+    }
+
+    /// Reset the bus and address the device with ROM code `rom`.
+    pub async fn match_rom(&self, rom: &Rom) -> Result<(), Error> {
+        let mut data = [0; 9];
+        data[0] = rom_cmd::MATCH_ROM;
+        data[1..].copy_from_slice(&rom.0);
+
+        let mut batch = self.resource.interface.batch();
+        let reset = batch.push(self.cmd_reset());
+        let write = batch.push(self.cmd_write_bits(72, &data));
+        let res = batch.run().await?;
+        if res.get(reset)? == 0 {
+            return Err(Error::NoPresence);
+        }
+        res.get(write)?;
+        Ok(())
+    }
+
+    /// Read the ROM code of the only device on the bus.
+    pub async fn read_rom(&self) -> Result<Rom, Error> {
+        let mut rom = [0; 8];
+        let mut queue = self.resource.interface.queue();
+        queue.push(self.cmd_reset()).await;
+        queue.push(self.cmd_write_bits(8, &[rom_cmd::READ_ROM])).await;
+        queue.push_read(self.cmd_read_bits(64), &mut rom).await;
+        queue.finish().await?;
+
+        let rom = Rom(rom);
+        if rom.0 == [0xff; 8] {
+            Err(Error::NoPresence)
+        } else if !rom.crc_valid() {
+            Err(Error::Crc)
+        } else {
+            Ok(rom)
+        }
+    }
+
+    /// Find the ROM codes of all devices on the bus.
+    pub async fn search(&self) -> Result<Vec<Rom>, Error> {
+        self.search_with(rom_cmd::SEARCH_ROM).await
+    }
+
+    /// Find the ROM codes of devices with an active alarm condition.
+    pub async fn search_alarm(&self) -> Result<Vec<Rom>, Error> {
+        self.search_with(rom_cmd::ALARM_SEARCH).await
+    }
+
+    async fn search_with(&self, command: u8) -> Result<Vec<Rom>, Error> {
+        let mut roms = Vec::new();
+        let mut rom = [0u8; 8];
+        let mut last_discrepancy = None;
+
+        loop {
+            let mut batch = self.resource.interface.batch();
+            let reset = batch.push(self.cmd_reset());
+            batch.push(self.cmd_write_bits(8, &[command]));
+            let read = batch.push(self.cmd_read_bits(2));
+            let res = batch.run().await?;
+            if res.get(reset)? == 0 {
+                return Ok(roms);
+            }
+            let mut pair = res.get(read)?[0];
+
+            let mut last_zero = None;
+            for bit in 0..64 {
+                let (id_bit, cmp_bit) = (pair & 1 != 0, pair & 2 != 0);
+                let dir = match (id_bit, cmp_bit) {
+                    (true, true) if roms.is_empty() && bit == 0 => return Ok(roms),
+                    (true, true) => return Err(Error::Search),
+                    (false, false) => {
+                        let dir = match last_discrepancy {
+                            Some(d) if bit < d => rom[bit / 8] & (1 << (bit % 8)) != 0,
+                            Some(d) => bit == d,
+                            None => false,
+                        };
+                        if !dir {
+                            last_zero = Some(bit);
+                        }
+                        dir
+                    }
+                    (id_bit, _) => id_bit,
+                };
+
+                if dir {
+                    rom[bit / 8] |= 1 << (bit % 8);
+                } else {
+                    rom[bit / 8] &= !(1 << (bit % 8));
+                }
+
+                let mut batch = self.resource.interface.batch();
+                let write = batch.push(self.cmd_write_bits(1, &[dir as u8]));
+                let next = (bit < 63).then(|| batch.push(self.cmd_read_bits(2)));
+                let res = batch.run().await?;
+                res.get(write)?;
+                if let Some(next) = next {
+                    pair = res.get(next)?[0];
+                }
+            }
+
+            let found = Rom(rom);
+            if !found.crc_valid() {
+                return Err(Error::Crc);
+            }
+            roms.push(found);
+
+            last_discrepancy = last_zero;
+            if last_discrepancy.is_none() {
+                return Ok(roms);
+            }
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use ::pio::{PioVersion, Program};
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, SliceResponse},
+};
+use viking_protocol::protocol::pio as protocol;
+
+/// Maximum number of FIFO words transferred by a single command.
+const CHUNK_WORDS: usize = 64;
+
+/// RP2040 / RP2350 PIO state machine.
+pub struct StateMachine {
+    resource: Resource,
+    desc: protocol::DescribeMode,
+}
+
+pub struct StateMachineBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("program does not fit in the state machine's instruction memory")]
+    ProgramTooLarge,
+
+    #[error("program origin {0} is not available to the state machine")]
+    InvalidOrigin(u8),
+
+    #[error("program requires PIO features not supported by the device")]
+    UnsupportedVersion,
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+impl ResourceMode for StateMachine {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = StateMachineBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        StateMachineBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+        }
+    }
+}
+
+impl StateMachineBuilder {
+    fn set_flag(&mut self, flag: protocol::ConfigFlags, value: bool) {
+        self.config.flags = if value {
+            self.config.flags.union(flag)
+        } else {
+            self.config.flags.difference(flag)
+        };
+    }
+
+    /// Divide the PIO clock by `int + frac / 256`.
+    pub fn clock_divider(mut self, int: u16, frac: u8) -> Self {
+        self.config.clock_div_int.set(int);
+        self.config.clock_div_frac = frac;
+        self
+    }
+
+    /// Pins used by `OUT` and `MOV PINS` instructions.
+    pub fn out_pins(mut self, base: u8, count: u8) -> Self {
+        self.config.out_base = base;
+        self.config.out_count = count;
+        self
+    }
+
+    /// Pins used by `SET` instructions.
+    pub fn set_pins(mut self, base: u8, count: u8) -> Self {
+        self.config.set_base = base;
+        self.config.set_count = count;
+        self
+    }
+
+    /// First pin used by `IN` and `WAIT PIN` instructions.
+    pub fn in_base(mut self, base: u8) -> Self {
+        self.config.in_base = base;
+        self
+    }
+
+    /// First pin used by side-set. The number of pins is defined by the program.
+    pub fn sideset_base(mut self, base: u8) -> Self {
+        self.config.sideset_base = base;
+        self
+    }
+
+    /// Pin tested by `JMP PIN`.
+    pub fn jmp_pin(mut self, pin: u8) -> Self {
+        self.config.jmp_pin = pin;
+        self
+    }
+
+    /// Pins driven as outputs when the mode is configured.
+    pub fn pindirs(mut self, outputs: u32) -> Self {
+        self.config.pindirs.set(outputs);
+        self
+    }
+
+    /// Automatically push the ISR to the RX FIFO after `threshold` bits (1-32).
+    pub fn autopush(mut self, threshold: u8) -> Self {
+        self.set_flag(protocol::ConfigFlags::AUTOPUSH, true);
+        self.config.push_threshold = threshold;
+        self
+    }
+
+    /// Automatically pull the OSR from the TX FIFO after `threshold` bits (1-32).
+    pub fn autopull(mut self, threshold: u8) -> Self {
+        self.set_flag(protocol::ConfigFlags::AUTOPULL, true);
+        self.config.pull_threshold = threshold;
+        self
+    }
+
+    /// Shift direction of the ISR. Defaults to right.
+    pub fn in_shift_right(mut self, right: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::IN_SHIFT_RIGHT, right);
+        self
+    }
+
+    /// Shift direction of the OSR. Defaults to right.
+    pub fn out_shift_right(mut self, right: bool) -> Self {
+        self.set_flag(protocol::ConfigFlags::OUT_SHIFT_RIGHT, right);
+        self
+    }
+
+    /// Join the RX FIFO into the TX FIFO, doubling its depth.
+    pub fn join_tx(mut self) -> Self {
+        self.set_flag(protocol::ConfigFlags::JOIN_RX, false);
+        self.set_flag(protocol::ConfigFlags::JOIN_TX, true);
+        self
+    }
+
+    /// Join the TX FIFO into the RX FIFO, doubling its depth.
+    pub fn join_rx(mut self) -> Self {
+        self.set_flag(protocol::ConfigFlags::JOIN_TX, false);
+        self.set_flag(protocol::ConfigFlags::JOIN_RX, true);
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<StateMachine, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        // A threshold of 32 bits is encoded as 0
+        let encode_threshold = |threshold: u8| match threshold {
+            1..=31 => Ok(threshold),
+            32 => Ok(0),
+            _ => Err("autopush and autopull thresholds must be 1-32"),
+        };
+        let flags = self.config.flags;
+        if flags.contains(protocol::ConfigFlags::AUTOPUSH) {
+            self.config.push_threshold = encode_threshold(self.config.push_threshold)?;
+        }
+        if flags.contains(protocol::ConfigFlags::AUTOPULL) {
+            self.config.pull_threshold = encode_threshold(self.config.pull_threshold)?;
+        }
+
+        let pin_count = desc.pin_count as u32;
+        let pin_range_valid = |base: u8, count: u8| {
+            (base as u32) < pin_count && base as u32 + count as u32 <= pin_count
+        };
+        if !pin_range_valid(self.config.out_base, self.config.out_count)
+            || !pin_range_valid(self.config.set_base, self.config.set_count)
+            || !pin_range_valid(self.config.in_base, 0)
+            || !pin_range_valid(self.config.sideset_base, 0)
+            || !pin_range_valid(self.config.jmp_pin, 1)
+            || (pin_count < 32 && self.config.pindirs.get() >> pin_count != 0)
+        {
+            Err("pin mapping outside of available pins")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        Ok(StateMachine { resource, desc })
+    }
+}
+
+impl StateMachine {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Clock frequency in Hz before the clock divider.
+    pub fn clock(&self) -> u32 {
+        self.desc.clock.get()
+    }
+
+    /// Load a program assembled with the [`pio`](::pio) crate and configure
+    /// the state machine's wrap and side-set settings for it.
+    ///
+    /// Programs without a fixed origin are placed at the start of the state
+    /// machine's instruction memory. Jump targets are relocated to the load
+    /// address. Returns the address of the first instruction.
+    ///
+    /// The state machine is left disabled with its program counter at the
+    /// start of the program.
+    pub async fn load<const N: usize>(&self, program: &Program<N>) -> Result<u8, Error> {
+        if program.version == PioVersion::V1 && !self.desc.flags.contains(protocol::ModeFlags::V1)
+        {
+            return Err(Error::UnsupportedVersion);
+        }
+
+        let base = self.desc.instr_base;
+        let end = base as usize + self.desc.instr_len as usize;
+        let origin = program.origin.unwrap_or(base);
+        if origin < base || origin as usize >= end {
+            return Err(Error::InvalidOrigin(origin));
+        }
+        if origin as usize + program.code.len() > end {
+            return Err(Error::ProgramTooLarge);
+        }
+
+        let code: Vec<u16> = program
+            .code
+            .iter()
+            .map(|&instr| {
+                // JMP has opcode 0 and the target address in the low 5 bits
+                if instr & 0xe000 == 0 {
+                    (instr & !0x1f) | ((instr & 0x1f) + origin as u16) & 0x1f
+                } else {
+                    instr
+                }
+            })
+            .collect();
+
+        let side_set = &program.side_set;
+        let mut sideset = side_set.bits() & protocol::sideset::COUNT_MASK;
+        if side_set.optional() {
+            sideset |= protocol::sideset::OPT;
+        }
+        if side_set.pindirs() {
+            sideset |= protocol::sideset::PINDIRS;
+        }
+
+        let header = [
+            origin,
+            program.wrap.target + origin,
+            program.wrap.source + origin,
+            sideset,
+        ];
+        let cmd = Command::new(
+            self.resource.id,
+            protocol::cmd::LOAD,
+            (header, &code[..]),
+            (),
+        );
+        self.resource.interface.run(cmd).await?;
+        Ok(origin)
+    }
+
+    pub fn cmd_control(&self, op: u8, arg: u16) -> Command<(u8, u16), ()> {
+        Command::new(self.resource.id, protocol::cmd::CONTROL, (op, arg), ())
+    }
+
+    pub async fn start(&self) -> Result<(), RequestError> {
+        let cmd = self.cmd_control(protocol::control::ENABLE, 0);
+        self.resource.interface.run(cmd).await
+    }
+
+    pub async fn stop(&self) -> Result<(), RequestError> {
+        let cmd = self.cmd_control(protocol::control::DISABLE, 0);
+        self.resource.interface.run(cmd).await
+    }
+
+    /// Clear the state machine's internal state, including shift registers
+    /// and counters, without changing the program counter.
+    pub async fn restart(&self) -> Result<(), RequestError> {
+        let cmd = self.cmd_control(protocol::control::RESTART, 0);
+        self.resource.interface.run(cmd).await
+    }
+
+    /// Immediately execute an encoded instruction.
+    pub async fn exec(&self, instr: u16) -> Result<(), RequestError> {
+        let cmd = self.cmd_control(protocol::control::EXEC, instr);
+        self.resource.interface.run(cmd).await
+    }
+
+    pub async fn clear_fifos(&self) -> Result<(), RequestError> {
+        let cmd = self.cmd_control(protocol::control::CLEAR_FIFOS, 0);
+        self.resource.interface.run(cmd).await
+    }
+
+    pub fn cmd_push<'a>(&self, words: &'a [u32]) -> Command<&'a [u32], ()> {
+        Command::new(self.resource.id, protocol::cmd::PUSH, words, ())
+    }
+
+    pub fn cmd_pull(&self, len: u8) -> Command<u8, SliceResponse> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::PULL,
+            len,
+            SliceResponse::new(len as usize * 4),
+        )
+    }
+
+    /// Write words to the TX FIFO, waiting for space as necessary.
+    pub async fn push(&self, words: &[u32]) -> Result<(), RequestError> {
+        let mut queue = self.resource.interface.queue();
+        for chunk in words.chunks(CHUNK_WORDS) {
+            queue.push(self.cmd_push(chunk)).await;
+        }
+        queue.finish().await
+    }
+
+    /// Read words from the RX FIFO, waiting for data as necessary.
+    pub async fn pull(&self, words: &mut [u32]) -> Result<(), RequestError> {
+        let mut buf = vec![0u8; words.len() * 4];
+        let mut queue = self.resource.interface.queue();
+        for chunk in buf.chunks_mut(CHUNK_WORDS * 4) {
+            let len = (chunk.len() / 4) as u8;
+            queue.push_read(self.cmd_pull(len), chunk).await;
+        }
+        queue.finish().await?;
+
+        for (word, bytes) in words.iter_mut().zip(buf.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+}
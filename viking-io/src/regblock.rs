@@ -0,0 +1,220 @@
+use thiserror::Error;
+use zerocopy::FromBytes;
+
+use crate::{
+    RequestError, Resource,
+    command::{Command, ScalarResponse, SliceResponse},
+    resource_mode,
+};
+use viking_protocol::protocol::regblock as protocol;
+
+/// Window of memory-mapped peripheral registers on the device.
+///
+/// Registers are addressed by their byte offset from [`base`](Self::base).
+pub struct RegisterBlock {
+    resource: Resource,
+}
+
+resource_mode!(RegisterBlock, RegisterBlockBuilder, protocol::PROTOCOL);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("offset {0:#x} is outside the register block")]
+    OutOfRange(u32),
+
+    #[error("offset {0:#x} is not 32-bit aligned")]
+    Unaligned(u32),
+
+    #[error("offset {0:#x} is read-only")]
+    ReadOnly(u32),
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+impl RegisterBlock {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    fn describe(&self) -> (protocol::DescribeMode, &[u8]) {
+        let mode = self.resource.mode_id.expect("register block is configured");
+        let bytes = self.resource.descriptor().mode(mode).unwrap().descriptor();
+        let desc: protocol::DescribeMode = self.resource.mode_descriptor(mode).unwrap();
+        let rest = bytes
+            .get(size_of::<protocol::DescribeMode>()..)
+            .unwrap_or(&[]);
+        (desc, rest)
+    }
+
+    /// Device address of offset 0.
+    pub fn base(&self) -> u32 {
+        self.describe().0.base.get()
+    }
+
+    /// Size of the window in bytes.
+    pub fn size(&self) -> u32 {
+        self.describe().0.size.get()
+    }
+
+    /// Access widths supported by the underlying bus.
+    pub fn widths(&self) -> protocol::WidthFlags {
+        self.describe().0.widths
+    }
+
+    /// Ranges of `(offset, len)` that may be read but not written.
+    pub fn read_only_ranges(&self) -> Vec<(u32, u32)> {
+        let (desc, mut rest) = self.describe();
+        let mut ranges = Vec::new();
+        for _ in 0..desc.read_only_ranges {
+            let Ok((range, next)) = protocol::ReadOnlyRange::read_from_prefix(rest) else {
+                break;
+            };
+            ranges.push((range.offset.get(), range.len.get()));
+            rest = next;
+        }
+        ranges
+    }
+
+    fn check_read(&self, offset: u32) -> Result<(), Error> {
+        if !offset.is_multiple_of(4) {
+            Err(Error::Unaligned(offset))
+        } else if offset.checked_add(4).is_none_or(|end| end > self.size()) {
+            Err(Error::OutOfRange(offset))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_write(&self, offset: u32) -> Result<(), Error> {
+        self.check_read(offset)?;
+        let read_only = self
+            .read_only_ranges()
+            .into_iter()
+            .any(|(start, len)| offset < start.saturating_add(len) && start < offset + 4);
+        if read_only {
+            Err(Error::ReadOnly(offset))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn cmd_read32(&self, offset: u32) -> Command<u32, ScalarResponse<u32>> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::READ32,
+            offset,
+            ScalarResponse::new(),
+        )
+    }
+
+    pub fn cmd_write32(&self, offset: u32, value: u32) -> Command<(u32, u32), ()> {
+        Command::new(self.resource.id, protocol::cmd::WRITE32, (offset, value), ())
+    }
+
+    pub fn cmd_modify(&self, offset: u32, mask: u32, value: u32) -> Command<(u32, u32, u32), ()> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::MODIFY,
+            (offset, mask, value),
+            (),
+        )
+    }
+
+    pub async fn read32(&self, offset: u32) -> Result<u32, Error> {
+        self.check_read(offset)?;
+        Ok(self.resource.interface.run(self.cmd_read32(offset)).await?)
+    }
+
+    pub async fn write32(&self, offset: u32, value: u32) -> Result<(), Error> {
+        self.check_write(offset)?;
+        Ok(self.resource.interface.run(self.cmd_write32(offset, value)).await?)
+    }
+
+    /// Atomically replace the bits selected by `mask` with the corresponding
+    /// bits of `value`.
+    pub async fn modify(&self, offset: u32, mask: u32, value: u32) -> Result<(), Error> {
+        self.check_write(offset)?;
+        Ok(self
+            .resource
+            .interface
+            .run(self.cmd_modify(offset, mask, value))
+            .await?)
+    }
+
+    /// Build a sequence of register accesses executed in as few command
+    /// batches as possible.
+    pub fn batch(&self) -> RegisterBatch<'_> {
+        RegisterBatch {
+            block: self,
+            ops: Vec::new(),
+        }
+    }
+}
+
+enum Op {
+    Read(u32),
+    Write(u32, u32),
+    Modify(u32, u32, u32),
+}
+
+pub struct RegisterBatch<'a> {
+    block: &'a RegisterBlock,
+    ops: Vec<Op>,
+}
+
+impl RegisterBatch<'_> {
+    /// Read a register. Its value is returned from [`run`](Self::run) in
+    /// order with the other reads.
+    pub fn read32(mut self, offset: u32) -> Self {
+        self.ops.push(Op::Read(offset));
+        self
+    }
+
+    pub fn write32(mut self, offset: u32, value: u32) -> Self {
+        self.ops.push(Op::Write(offset, value));
+        self
+    }
+
+    pub fn modify(mut self, offset: u32, mask: u32, value: u32) -> Self {
+        self.ops.push(Op::Modify(offset, mask, value));
+        self
+    }
+
+    /// Validate and execute the accesses, returning the values of the reads.
+    pub async fn run(self) -> Result<Vec<u32>, Error> {
+        let block = self.block;
+        for op in &self.ops {
+            match *op {
+                Op::Read(offset) => block.check_read(offset)?,
+                Op::Write(offset, _) | Op::Modify(offset, _, _) => block.check_write(offset)?,
+            }
+        }
+
+        let reads = self.ops.iter().filter(|op| matches!(op, Op::Read(_))).count();
+        let mut values = vec![[0u8; 4]; reads];
+        let mut dest = values.iter_mut();
+
+        let mut queue = block.resource.interface.queue();
+        for op in &self.ops {
+            match *op {
+                Op::Read(offset) => {
+                    let cmd = Command::new(
+                        block.resource.id,
+                        protocol::cmd::READ32,
+                        offset,
+                        SliceResponse::new(4),
+                    );
+                    queue.push_read(cmd, dest.next().unwrap()).await;
+                }
+                Op::Write(offset, value) => queue.push(block.cmd_write32(offset, value)).await,
+                Op::Modify(offset, mask, value) => {
+                    queue.push(block.cmd_modify(offset, mask, value)).await
+                }
+            }
+        }
+        queue.finish().await?;
+
+        Ok(values.into_iter().map(u32::from_le_bytes).collect())
+    }
+}
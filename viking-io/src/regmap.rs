@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use embedded_hal_async::{i2c::Operation as I2cOperation, spi::Operation as SpiOperation};
+use thiserror::Error;
+
+use crate::{i2c, spi};
+
+/// Byte order of multi-byte register values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Big,
+    Little,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    I2c(#[from] i2c::Error),
+
+    #[error("{0}")]
+    Spi(#[from] spi::Error),
+}
+
+enum Bus {
+    I2c {
+        controller: Arc<i2c::Controller>,
+        address: u8,
+    },
+    Spi(spi::Device),
+    SpiShared(spi::BusDevice),
+}
+
+/// Registers of a device on an I2C or SPI bus.
+///
+/// Each access is a transaction sending the register address, most
+/// significant byte first, followed by the values written, or followed by
+/// reading the values. The read or write mask is ORed into the first address
+/// byte, e.g. `0x80` for SPI devices that set the top bit for reads.
+///
+/// I2C transactions are checked against the controller's capabilities like
+/// [`embedded_hal_async::i2c::I2c::transaction`], and SPI devices must use
+/// 8-bit words.
+///
+/// Each method sends all of its transactions in a single
+/// [`CommandQueue`](crate::CommandQueue). Accesses to consecutive registers
+/// are combined into one transaction if the device auto-increments the
+/// register address, and are otherwise sent as a separate transaction per
+/// register.
+pub struct RegisterMap {
+    bus: Bus,
+    address_width: usize,
+    data_width: usize,
+    endian: Endian,
+    read_mask: u8,
+    write_mask: u8,
+    auto_increment: Option<u8>,
+}
+
+impl RegisterMap {
+    fn new(bus: Bus) -> Self {
+        Self {
+            bus,
+            address_width: 1,
+            data_width: 1,
+            endian: Endian::Big,
+            read_mask: 0,
+            write_mask: 0,
+            auto_increment: None,
+        }
+    }
+
+    /// Registers of the device at the 7-bit `address`.
+    pub fn i2c(controller: Arc<i2c::Controller>, address: u8) -> Self {
+        Self::new(Bus::I2c {
+            controller,
+            address,
+        })
+    }
+
+    /// Registers of a SPI device.
+    pub fn spi(device: spi::Device) -> Self {
+        Self::new(Bus::Spi(device))
+    }
+
+    /// Registers of a device on a shared [`spi::SpiBus`].
+    pub fn spi_bus(device: spi::BusDevice) -> Self {
+        Self::new(Bus::SpiShared(device))
+    }
+
+    /// Bytes in a register address, from 1 to 4. Defaults to 1.
+    pub fn address_width(mut self, bytes: usize) -> Self {
+        assert!((1..=4).contains(&bytes));
+        self.address_width = bytes;
+        self
+    }
+
+    /// Bytes in a register value, from 1 to 4. Defaults to 1.
+    pub fn data_width(mut self, bytes: usize) -> Self {
+        assert!((1..=4).contains(&bytes));
+        self.data_width = bytes;
+        self
+    }
+
+    /// Byte order of register values. Defaults to big endian.
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// Bits set in the first address byte for reads.
+    pub fn read_mask(mut self, mask: u8) -> Self {
+        self.read_mask = mask;
+        self
+    }
+
+    /// Bits set in the first address byte for writes.
+    pub fn write_mask(mut self, mask: u8) -> Self {
+        self.write_mask = mask;
+        self
+    }
+
+    /// Access consecutive registers in one transaction, setting `mask` in the
+    /// first address byte. Use a mask of 0 for devices that always
+    /// auto-increment.
+    pub fn auto_increment(mut self, mask: u8) -> Self {
+        self.auto_increment = Some(mask);
+        self
+    }
+
+    fn header(&self, reg: u32, mask: u8, count: usize) -> Vec<u8> {
+        let mut header = reg.to_be_bytes()[4 - self.address_width..].to_vec();
+        header[0] |= mask;
+        if count > 1 {
+            header[0] |= self.auto_increment.unwrap_or(0);
+        }
+        header
+    }
+
+    fn encode(&self, value: u32, out: &mut Vec<u8>) {
+        match self.endian {
+            Endian::Big => out.extend_from_slice(&value.to_be_bytes()[4 - self.data_width..]),
+            Endian::Little => out.extend_from_slice(&value.to_le_bytes()[..self.data_width]),
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> u32 {
+        let mut bytes = [0; 4];
+        match self.endian {
+            Endian::Big => {
+                bytes[4 - data.len()..].copy_from_slice(data);
+                u32::from_be_bytes(bytes)
+            }
+            Endian::Little => {
+                bytes[..data.len()].copy_from_slice(data);
+                u32::from_le_bytes(bytes)
+            }
+        }
+    }
+
+    /// Run transactions each writing an address header followed by writing
+    /// or reading data, in a single command queue.
+    async fn run(&self, accesses: &mut [Access<'_>]) -> Result<(), Error> {
+        match &self.bus {
+            Bus::I2c {
+                controller,
+                address,
+            } => {
+                let mut ops: Vec<[I2cOperation; 2]> = accesses
+                    .iter_mut()
+                    .map(|a| {
+                        let data = match &mut a.data {
+                            Data::Write(w) => I2cOperation::Write(w),
+                            Data::Read(r) => I2cOperation::Read(r),
+                        };
+                        [I2cOperation::Write(&a.header), data]
+                    })
+                    .collect();
+                let mut transactions: Vec<&mut [I2cOperation]> =
+                    ops.iter_mut().map(|o| &mut o[..]).collect();
+                controller
+                    .run_transactions(i2c::Address::Seven(*address), &mut transactions)
+                    .await?;
+            }
+            Bus::Spi(_) | Bus::SpiShared(_) => {
+                let mut ops: Vec<[SpiOperation<u8>; 2]> = accesses
+                    .iter_mut()
+                    .map(|a| {
+                        let data = match &mut a.data {
+                            Data::Write(w) => SpiOperation::Write(w),
+                            Data::Read(r) => SpiOperation::Read(r),
+                        };
+                        [SpiOperation::Write(&a.header), data]
+                    })
+                    .collect();
+                let mut transactions: Vec<&mut [SpiOperation<u8>]> =
+                    ops.iter_mut().map(|o| &mut o[..]).collect();
+                match &self.bus {
+                    Bus::Spi(device) => device.run_transactions(&mut transactions).await?,
+                    Bus::SpiShared(device) => device.run_transactions(&mut transactions).await?,
+                    Bus::I2c { .. } => unreachable!(),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn read(&self, reg: u32) -> Result<u32, Error> {
+        let mut value = [0];
+        self.bulk_read(reg, &mut value).await?;
+        Ok(value[0])
+    }
+
+    pub async fn write(&self, reg: u32, value: u32) -> Result<(), Error> {
+        self.bulk_write(reg, &[value]).await
+    }
+
+    /// Read the register and write back its value with the bits in `mask`
+    /// replaced by those of `value`, skipping the write if unchanged.
+    ///
+    /// The read and the write are sent in separate command queues, so this is
+    /// not atomic with respect to other users of the bus.
+    pub async fn update_bits(&self, reg: u32, mask: u32, value: u32) -> Result<(), Error> {
+        let old = self.read(reg).await?;
+        let new = old & !mask | value & mask;
+        if new != old {
+            self.write(reg, new).await?;
+        }
+        Ok(())
+    }
+
+    /// Read consecutive registers starting at `reg`.
+    pub async fn bulk_read(&self, reg: u32, values: &mut [u32]) -> Result<(), Error> {
+        let mut buf = vec![0; values.len() * self.data_width];
+        let mut accesses: Vec<Access> = if self.auto_increment.is_some() {
+            vec![Access {
+                header: self.header(reg, self.read_mask, values.len()),
+                data: Data::Read(&mut buf),
+            }]
+        } else {
+            buf.chunks_mut(self.data_width)
+                .enumerate()
+                .map(|(i, dest)| Access {
+                    header: self.header(reg + i as u32, self.read_mask, 1),
+                    data: Data::Read(dest),
+                })
+                .collect()
+        };
+        self.run(&mut accesses).await?;
+        drop(accesses);
+
+        for (value, data) in values.iter_mut().zip(buf.chunks(self.data_width)) {
+            *value = self.decode(data);
+        }
+        Ok(())
+    }
+
+    /// Write consecutive registers starting at `reg`.
+    pub async fn bulk_write(&self, reg: u32, values: &[u32]) -> Result<(), Error> {
+        let mut accesses = if self.auto_increment.is_some() {
+            let mut data = Vec::new();
+            for &value in values {
+                self.encode(value, &mut data);
+            }
+            vec![Access {
+                header: self.header(reg, self.write_mask, values.len()),
+                data: Data::Write(data),
+            }]
+        } else {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| {
+                    let mut data = Vec::new();
+                    self.encode(value, &mut data);
+                    Access {
+                        header: self.header(reg + i as u32, self.write_mask, 1),
+                        data: Data::Write(data),
+                    }
+                })
+                .collect()
+        };
+        self.run(&mut accesses).await
+    }
+}
+
+/// Transaction writing a register address, followed by the register values.
+struct Access<'a> {
+    header: Vec<u8>,
+    data: Data<'a>,
+}
+
+enum Data<'a> {
+    Write(Vec<u8>),
+    Read(&'a mut [u8]),
+}
@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use embedded_hal_async::i2c::Operation;
+use thiserror::Error;
+
+use crate::{RequestError, i2c};
+
+/// Maximum number of data bytes in a block transfer.
+pub const BLOCK_MAX: usize = 32;
+
+/// Address a device writes to when sending a Host Notify message.
+pub const HOST_ADDRESS: u8 = 0x08;
+
+/// Address read to find devices asserting SMBALERT#.
+pub const ALERT_RESPONSE_ADDRESS: u8 = 0x0C;
+
+/// CRC-8 (polynomial x^8 + x^2 + x + 1) used for Packet Error Checking.
+pub fn pec(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("packet error check mismatch")]
+    Pec,
+
+    #[error("invalid block length {0}")]
+    BlockLength(usize),
+
+    #[error("{0}")]
+    I2c(#[from] i2c::Error),
+}
+
+impl From<RequestError> for Error {
+    fn from(e: RequestError) -> Self {
+        Self::I2c(e.into())
+    }
+}
+
+/// Host Notify message sent by a device to [`HOST_ADDRESS`], as received by
+/// an [`i2c::Target`] in forward mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostNotify {
+    /// 7-bit address of the device that sent the message.
+    pub address: u8,
+    pub data: u16,
+}
+
+impl HostNotify {
+    /// Parse the bytes written to the host address.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        match *data {
+            [address, lo, hi] => Some(Self {
+                address: address >> 1,
+                data: u16::from_le_bytes([lo, hi]),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// SMBus transactions on an I2C controller.
+///
+/// Transactions are checked against the controller's capabilities like
+/// [`embedded_hal_async::i2c::I2c::transaction`], failing with
+/// [`i2c::Error::Unsupported`] if the controller cannot perform them. Methods
+/// take the 7-bit address of the target device.
+pub struct Smbus<C = Arc<i2c::Controller>> {
+    controller: C,
+    pec: bool,
+}
+
+impl<C: AsRef<i2c::Controller>> Smbus<C> {
+    pub fn new(controller: C) -> Self {
+        Self {
+            controller,
+            pec: false,
+        }
+    }
+
+    /// Append a PEC byte to writes and check the PEC byte of reads.
+    pub fn pec(mut self, enable: bool) -> Self {
+        self.pec = enable;
+        self
+    }
+
+    async fn transaction(
+        &self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        Ok(self
+            .controller
+            .as_ref()
+            .run_transaction(i2c::Address::Seven(address), operations)
+            .await?)
+    }
+
+    /// Write `data` after the address, followed by the PEC if enabled.
+    async fn write_transaction(&self, address: u8, data: &[u8]) -> Result<(), Error> {
+        let mut buf = data.to_vec();
+        if self.pec {
+            buf.push(pec(&[&[address << 1], data].concat()));
+        }
+        self.transaction(address, &mut [Operation::Write(&buf)])
+            .await
+    }
+
+    /// Write `command`, then read `len` bytes after a repeated start and check
+    /// a trailing PEC byte if `check_pec` is set. With an empty `command`, only read.
+    async fn read_transaction(
+        &self,
+        address: u8,
+        command: &[u8],
+        len: usize,
+        check_pec: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let addr = address << 1;
+        let mut buf = vec![0; len + check_pec as usize];
+
+        if command.is_empty() {
+            self.transaction(address, &mut [Operation::Read(&mut buf)])
+                .await?;
+        } else {
+            let mut ops = [Operation::Write(command), Operation::Read(&mut buf)];
+            self.transaction(address, &mut ops).await?;
+        }
+
+        if check_pec {
+            let received = buf.pop().unwrap();
+            let mut covered = Vec::new();
+            if !command.is_empty() {
+                covered.push(addr);
+                covered.extend_from_slice(command);
+            }
+            covered.push(addr | 1);
+            covered.extend_from_slice(&buf);
+            if pec(&covered) != received {
+                return Err(Error::Pec);
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Send the address with the direction bit set to `read`, without data.
+    ///
+    /// A quick write requires the controller's `ZERO_LEN_WRITE` capability.
+    pub async fn quick(&self, address: u8, read: bool) -> Result<(), Error> {
+        let op = if read {
+            Operation::Read(&mut [])
+        } else {
+            Operation::Write(&[])
+        };
+        self.transaction(address, &mut [op]).await
+    }
+
+    pub async fn send_byte(&self, address: u8, value: u8) -> Result<(), Error> {
+        self.write_transaction(address, &[value]).await
+    }
+
+    pub async fn receive_byte(&self, address: u8) -> Result<u8, Error> {
+        Ok(self.read_transaction(address, &[], 1, self.pec).await?[0])
+    }
+
+    pub async fn write_byte(&self, address: u8, command: u8, value: u8) -> Result<(), Error> {
+        self.write_transaction(address, &[command, value]).await
+    }
+
+    pub async fn read_byte(&self, address: u8, command: u8) -> Result<u8, Error> {
+        Ok(self
+            .read_transaction(address, &[command], 1, self.pec)
+            .await?[0])
+    }
+
+    pub async fn write_word(&self, address: u8, command: u8, value: u16) -> Result<(), Error> {
+        let [lo, hi] = value.to_le_bytes();
+        self.write_transaction(address, &[command, lo, hi]).await
+    }
+
+    pub async fn read_word(&self, address: u8, command: u8) -> Result<u16, Error> {
+        let data = self
+            .read_transaction(address, &[command], 2, self.pec)
+            .await?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    /// Write a word and read a word in response.
+    pub async fn process_call(&self, address: u8, command: u8, value: u16) -> Result<u16, Error> {
+        let [lo, hi] = value.to_le_bytes();
+        let data = self
+            .read_transaction(address, &[command, lo, hi], 2, self.pec)
+            .await?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    pub async fn block_write(&self, address: u8, command: u8, data: &[u8]) -> Result<(), Error> {
+        if data.len() > BLOCK_MAX {
+            return Err(Error::BlockLength(data.len()));
+        }
+        let buf = [&[command, data.len() as u8], data].concat();
+        self.write_transaction(address, &buf).await
+    }
+
+    /// Read a block of up to [`BLOCK_MAX`] bytes.
+    ///
+    /// If the controller cannot split a transaction across batches, the
+    /// transaction is sent before the length is known, so a full-length block
+    /// is clocked out of the device and the bytes past the reported length
+    /// are discarded.
+    pub async fn block_read(&self, address: u8, command: u8) -> Result<Vec<u8>, Error> {
+        self.block_transaction(address, &[command]).await
+    }
+
+    /// Write a block and read a block in response.
+    pub async fn block_process_call(
+        &self,
+        address: u8,
+        command: u8,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        if data.len() > BLOCK_MAX {
+            return Err(Error::BlockLength(data.len()));
+        }
+        let buf = [&[command, data.len() as u8], data].concat();
+        self.block_transaction(address, &buf).await
+    }
+
+    async fn block_transaction(&self, address: u8, command: &[u8]) -> Result<Vec<u8>, Error> {
+        // The PEC follows the number of bytes given by the length
+        let mut data = self
+            .controller
+            .as_ref()
+            .read_counted(address, command, BLOCK_MAX, self.pec as usize)
+            .await?;
+
+        let len = data[0] as usize;
+        if len > BLOCK_MAX {
+            return Err(Error::BlockLength(len));
+        }
+        if self.pec {
+            let addr = address << 1;
+            let covered = [&[addr], command, &[addr | 1], &data[..1 + len]].concat();
+            if pec(&covered) != data[1 + len] {
+                return Err(Error::Pec);
+            }
+        }
+        data.truncate(1 + len);
+        data.remove(0);
+        Ok(data)
+    }
+
+    /// Read the Alert Response Address, returning the 7-bit address of the
+    /// device asserting SMBALERT# with the lowest address, or `None` if no
+    /// device responds.
+    pub async fn alert_response(&self) -> Result<Option<u8>, Error> {
+        match self
+            .read_transaction(ALERT_RESPONSE_ADDRESS, &[], 1, self.pec)
+            .await
+        {
+            Ok(data) => Ok(Some(data[0] >> 1)),
+            Err(Error::I2c(i2c::Error::AddrNack)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -1,21 +1,101 @@
-use std::{sync::Arc, u16};
+use std::{sync::Arc, time::Duration};
+
+use futures_lite::{Stream, stream};
+use nusb::transfer::TransferError;
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use embedded_hal_async::spi::Operation;
 
 use crate::{
-    RequestError, Resource, cmd_delay,
-    command::{Command, SliceResponse},
+    CommandQueue, Interface, RequestError, Resource, ResourceMode, cmd_delay,
+    command::{Command, Raw, SliceResponse},
     gpio::Gpio,
     resource_mode,
 };
-use viking_protocol::protocol::spi::{controller, sck_pin, sdi_pin, sdo_pin};
+use viking_protocol::protocol::spi::{controller, cs_pin, sck_pin, sdi_pin, sdo_pin, target};
 
 pub struct Controller {
+    pub(crate) resource: Resource,
+    word_bits: u8,
+}
+
+pub struct ControllerBuilder {
     resource: Resource,
+    mode: u8,
+    config: controller::Config,
 }
 
-resource_mode!(Controller, ControllerBuilder, controller::PROTOCOL);
+impl ResourceMode for Controller {
+    const PROTOCOL: u16 = controller::PROTOCOL;
+    type Builder = ControllerBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        ControllerBuilder {
+            resource,
+            mode,
+            config: controller::Config::default(),
+        }
+    }
+}
+
+/// Range of supported bits per word.
+fn word_bits_range(desc: &controller::DescribeMode) -> std::ops::RangeInclusive<u8> {
+    if desc.max_word_bits == 0 {
+        8..=8
+    } else {
+        desc.min_word_bits..=desc.max_word_bits
+    }
+}
+
+impl ControllerBuilder {
+    /// Bits per word, from 4 to 32 if supported. Defaults to 8.
+    ///
+    /// Words of more than 8 bits are transferred with the
+    /// [`SpiBus<u16>`](embedded_hal_async::spi::SpiBus) or `SpiBus<u32>`
+    /// implementation.
+    pub fn word_size(mut self, bits: u8) -> Self {
+        self.config.word_bits = bits;
+        self
+    }
+
+    pub async fn enable(self) -> Result<Controller, crate::Error> {
+        let desc: controller::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        if !word_bits_range(&desc).contains(&self.config.word_bits) {
+            Err("word size not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        Ok(Controller {
+            resource,
+            word_bits: self.config.word_bits,
+        })
+    }
+}
 
 impl Controller {
-    pub fn cmd_read<'a>(&self, len: u8) -> Command<u8, SliceResponse> {
+    /// Bits per word.
+    pub fn word_size(&self) -> u8 {
+        self.word_bits
+    }
+
+    /// Check that words fit in a byte, as transferred by the `u8` interfaces.
+    pub(crate) fn check_byte_words(&self) -> Result<(), Error> {
+        if self.word_bits > 8 {
+            return Err(Error::WordSize);
+        }
+        Ok(())
+    }
+
+    pub fn cmd_read(&self, len: u8) -> Command<u8, SliceResponse> {
         Command::new(
             self.resource.id,
             controller::cmd::READ,
@@ -43,18 +123,160 @@ impl Controller {
     }
 }
 
-#[derive(Debug)]
-pub struct Error;
+/// Number of data lines used by a phase of a multi-lane transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lanes {
+    #[default]
+    Single,
+    Dual,
+    Quad,
+}
+
+impl Lanes {
+    fn value(self) -> u8 {
+        match self {
+            Lanes::Single => controller::lanes::SINGLE,
+            Lanes::Dual => controller::lanes::DUAL,
+            Lanes::Quad => controller::lanes::QUAD,
+        }
+    }
+}
+
+/// Phases of a multi-lane (dual / quad SPI) transfer, as used by serial flash.
+///
+/// Phases are shifted in order: instruction, address, dummy cycles, data.
+/// Omitted phases are skipped, so a transfer with only a data phase continues
+/// the previous one while chip select stays asserted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Phases {
+    pub instruction: Option<(Lanes, u8)>,
+    pub address: Option<(Lanes, u32)>,
+    /// Use a 4-byte address phase instead of 3 bytes.
+    pub address_4byte: bool,
+    pub dummy_cycles: u8,
+    pub data: Lanes,
+}
+
+impl Phases {
+    fn header(&self, write: bool, len: u16) -> [u8; 9] {
+        let mut flags = self.data.value() << controller::lanes::DATA_SHIFT;
+        if let Some((lanes, _)) = self.instruction {
+            flags |= lanes.value() << controller::lanes::INSTRUCTION_SHIFT;
+        }
+        if let Some((lanes, _)) = self.address {
+            flags |= lanes.value() << controller::lanes::ADDRESS_SHIFT;
+        }
+        if self.address_4byte {
+            flags |= controller::multi::ADDRESS_4BYTE;
+        }
+        if write {
+            flags |= controller::multi::WRITE;
+        }
+        let header = controller::MultiHeader {
+            flags,
+            instruction: self.instruction.map_or(0, |(_, i)| i),
+            address: self.address.map_or(0, |(_, a)| a).into(),
+            dummy_cycles: self.dummy_cycles,
+            len: len.into(),
+        };
+        let mut buf = [0; 9];
+        buf.copy_from_slice(header.as_bytes());
+        buf
+    }
+
+    fn max_lanes(&self) -> Lanes {
+        [
+            self.instruction.map(|p| p.0),
+            self.address.map(|p| p.0),
+            Some(self.data),
+        ]
+        .into_iter()
+        .flatten()
+        .max_by_key(|l| l.value())
+        .unwrap_or_default()
+    }
+}
+
+impl Controller {
+    /// Whether the controller supports transfers using `lanes` data lines.
+    pub fn supports_lanes(&self, lanes: Lanes) -> bool {
+        let mode = self.resource.mode_id.expect("controller is configured");
+        let desc: Option<controller::DescribeMode> = self.resource.mode_descriptor(mode);
+        let flags = desc.map_or(controller::ModeFlags::EMPTY, |d| d.flags);
+        match lanes {
+            Lanes::Single => true,
+            Lanes::Dual => flags.contains(controller::ModeFlags::DUAL),
+            Lanes::Quad => flags.contains(controller::ModeFlags::QUAD),
+        }
+    }
+
+    /// Multi-lane transfer reading `len` bytes in the data phase.
+    pub fn cmd_multi_read(&self, phases: &Phases, len: u16) -> Command<[u8; 9], SliceResponse> {
+        debug_assert!(self.supports_lanes(phases.max_lanes()));
+        Command::new(
+            self.resource.id,
+            controller::cmd::MULTI,
+            phases.header(false, len),
+            SliceResponse::new(len as usize),
+        )
+    }
+
+    /// Multi-lane transfer writing `data` in the data phase.
+    pub fn cmd_multi_write<'a>(
+        &self,
+        phases: &Phases,
+        data: &'a [u8],
+    ) -> Command<([u8; 9], Raw<'a>), ()> {
+        debug_assert!(self.supports_lanes(phases.max_lanes()));
+        Command::new(
+            self.resource.id,
+            controller::cmd::MULTI,
+            (phases.header(true, data.len() as u16), Raw(data)),
+            (),
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("skipped due to prior error")]
+    PriorError,
+
+    #[error("unexpected response status {0:02X}")]
+    Status(u8),
+
+    #[error("{0}")]
+    Protocol(&'static str),
+
+    #[error("{0}")]
+    Usb(#[from] TransferError),
+
+    #[error("word type does not match the controller's word size")]
+    WordSize,
+
+    #[error("{0}")]
+    Configure(#[source] crate::Error),
+}
 
 impl embedded_hal_async::spi::Error for Error {
     fn kind(&self) -> embedded_hal_async::spi::ErrorKind {
-        todo!()
+        use embedded_hal_async::spi::ErrorKind;
+
+        match self {
+            Error::WordSize => ErrorKind::FrameFormat,
+            _ => ErrorKind::Other,
+        }
     }
 }
 
 impl From<RequestError> for Error {
-    fn from(value: RequestError) -> Self {
-        Error
+    fn from(v: RequestError) -> Self {
+        match v {
+            RequestError::PriorError => Self::PriorError,
+            RequestError::Protocol(msg) => Self::Protocol(msg),
+            RequestError::Usb(e) => Self::Usb(e),
+            RequestError::Status(status) => Self::Status(status),
+        }
     }
 }
 
@@ -64,6 +286,7 @@ impl embedded_hal_async::spi::ErrorType for Controller {
 
 impl embedded_hal_async::spi::SpiBus for Controller {
     async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_byte_words()?;
         let mut queue = self.resource.interface.queue();
         for dest in words.chunks_mut(255) {
             queue
@@ -74,6 +297,7 @@ impl embedded_hal_async::spi::SpiBus for Controller {
     }
 
     async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.check_byte_words()?;
         let mut queue = self.resource.interface.queue();
         for src in words.chunks(255) {
             queue.push(self.cmd_write(src)).await;
@@ -82,6 +306,7 @@ impl embedded_hal_async::spi::SpiBus for Controller {
     }
 
     async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.check_byte_words()?;
         assert_eq!(read.len(), write.len());
         let mut queue = self.resource.interface.queue();
         for (dest, src) in read.chunks_mut(255).zip(write.chunks(255)) {
@@ -91,6 +316,7 @@ impl embedded_hal_async::spi::SpiBus for Controller {
     }
 
     async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_byte_words()?;
         let mut queue = self.resource.interface.queue();
         for chunk in words.chunks_mut(255) {
             queue
@@ -105,6 +331,239 @@ impl embedded_hal_async::spi::SpiBus for Controller {
     }
 }
 
+/// Implement `SpiBus` for words of more than 8 bits, packed little-endian
+/// into the command data.
+macro_rules! spi_bus_word {
+    ($word:ty, $bits:pat) => {
+        impl embedded_hal_async::spi::SpiBus<$word> for Controller {
+            async fn read(&mut self, words: &mut [$word]) -> Result<(), Self::Error> {
+                const N: usize = size_of::<$word>();
+                if !matches!(self.word_bits, $bits) {
+                    return Err(Error::WordSize);
+                }
+                let mut buf = vec![0; words.len() * N];
+                let mut queue = self.resource.interface.queue();
+                for dest in buf.chunks_mut(255 / N * N) {
+                    queue.push_read(self.cmd_read(dest.len() as u8), dest).await;
+                }
+                queue.finish().await?;
+                for (w, b) in words.iter_mut().zip(buf.chunks_exact(N)) {
+                    *w = <$word>::from_le_bytes(b.try_into().unwrap());
+                }
+                Ok(())
+            }
+
+            async fn write(&mut self, words: &[$word]) -> Result<(), Self::Error> {
+                const N: usize = size_of::<$word>();
+                if !matches!(self.word_bits, $bits) {
+                    return Err(Error::WordSize);
+                }
+                let buf: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+                let mut queue = self.resource.interface.queue();
+                for src in buf.chunks(255 / N * N) {
+                    queue.push(self.cmd_write(src)).await;
+                }
+                Ok(queue.finish().await?)
+            }
+
+            async fn transfer(
+                &mut self,
+                read: &mut [$word],
+                write: &[$word],
+            ) -> Result<(), Self::Error> {
+                assert_eq!(read.len(), write.len());
+                read.copy_from_slice(write);
+                self.transfer_in_place(read).await
+            }
+
+            async fn transfer_in_place(&mut self, words: &mut [$word]) -> Result<(), Self::Error> {
+                const N: usize = size_of::<$word>();
+                if !matches!(self.word_bits, $bits) {
+                    return Err(Error::WordSize);
+                }
+                let mut buf: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+                let mut queue = self.resource.interface.queue();
+                for chunk in buf.chunks_mut(255 / N * N) {
+                    queue
+                        .push_read_in_place(chunk, |b| self.cmd_transfer(b))
+                        .await;
+                }
+                queue.finish().await?;
+                for (w, b) in words.iter_mut().zip(buf.chunks_exact(N)) {
+                    *w = <$word>::from_le_bytes(b.try_into().unwrap());
+                }
+                Ok(())
+            }
+
+            async fn flush(&mut self) -> Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+    };
+}
+
+spi_bus_word!(u16, 9..=16);
+spi_bus_word!(u32, 17..=32);
+
+/// SPI target (peripheral) that reports transactions framed by chip select.
+///
+/// The device shifts out the response loaded with
+/// [`set_response`](Target::set_response) at the start of every transaction,
+/// followed by the configured fill byte.
+pub struct Target {
+    resource: Resource,
+    buffer_len: u16,
+}
+
+pub struct TargetBuilder {
+    resource: Resource,
+    mode: u8,
+    config: target::Config,
+}
+
+impl ResourceMode for Target {
+    const PROTOCOL: u16 = target::PROTOCOL;
+    type Builder = TargetBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        TargetBuilder {
+            resource,
+            mode,
+            config: target::Config::default(),
+        }
+    }
+}
+
+impl TargetBuilder {
+    /// SPI mode 0-3 (default 0).
+    pub fn spi_mode(mut self, mode: u8) -> Self {
+        let lsb_first = self.config.flags.contains(target::ConfigFlags::LSB_FIRST);
+        self.config.flags = target::ConfigFlags::for_mode(mode);
+        if lsb_first {
+            self.config.flags = self.config.flags.union(target::ConfigFlags::LSB_FIRST);
+        }
+        self
+    }
+
+    /// Shift least significant bit first.
+    pub fn lsb_first(mut self) -> Self {
+        self.config.flags = self.config.flags.union(target::ConfigFlags::LSB_FIRST);
+        self
+    }
+
+    /// Byte shifted out after the end of the response (default 0xFF).
+    pub fn fill(mut self, fill: u8) -> Self {
+        self.config.fill = fill;
+        self
+    }
+
+    pub async fn enable(self) -> Result<Target, crate::Error> {
+        use target::{ConfigFlags, ModeFlags};
+
+        let desc: target::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let mode_flag = match self.config.flags.mode() {
+            0 => ModeFlags::MODE0,
+            1 => ModeFlags::MODE1,
+            2 => ModeFlags::MODE2,
+            _ => ModeFlags::MODE3,
+        };
+        if !desc.flags.contains(mode_flag) {
+            Err("SPI mode not supported")?
+        }
+
+        let bit_order = if self.config.flags.contains(ConfigFlags::LSB_FIRST) {
+            ModeFlags::LSB_FIRST
+        } else {
+            ModeFlags::MSB_FIRST
+        };
+        if !desc.flags.contains(bit_order) {
+            Err("bit order not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        resource
+            .subscribe_events(|evt, data| match evt {
+                target::evt::DATA | target::evt::END => Some(1 + *data.first()? as usize),
+                _ => Some(0),
+            })
+            .await;
+
+        Ok(Target {
+            resource,
+            buffer_len: desc.buffer_len.get(),
+        })
+    }
+}
+
+impl Target {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Maximum length of the response in bytes.
+    pub fn buffer_len(&self) -> u16 {
+        self.buffer_len
+    }
+
+    pub fn cmd_load<'a>(&self, offset: u16, data: &'a [u8]) -> Command<(u16, &'a [u8]), ()> {
+        Command::new(self.resource.id, target::cmd::LOAD, (offset, data), ())
+    }
+
+    pub fn cmd_set_len(&self, len: u16) -> Command<u16, ()> {
+        Command::new(self.resource.id, target::cmd::SET_LEN, len, ())
+    }
+
+    /// Set the bytes shifted out at the start of each subsequent transaction.
+    pub async fn set_response(&self, data: &[u8]) -> Result<(), RequestError> {
+        if data.len() > self.buffer_len as usize {
+            return Err(RequestError::Protocol("response exceeds target buffer"));
+        }
+        let mut queue = self.resource.interface.queue();
+        for (i, chunk) in data.chunks(255).enumerate() {
+            queue.push(self.cmd_load((i * 255) as u16, chunk)).await;
+        }
+        queue.push(self.cmd_set_len(data.len() as u16)).await;
+        queue.finish().await
+    }
+
+    /// Wait for the controller to complete a transaction, and return the
+    /// bytes it shifted in.
+    ///
+    /// Returns an error if the device dropped received data because it was
+    /// not collected quickly enough.
+    pub async fn next_transaction(&self) -> Result<Vec<u8>, RequestError> {
+        let mut received = Vec::new();
+        loop {
+            let event = self.resource.next_event().await?;
+            match event.evt {
+                target::evt::DATA => received.extend_from_slice(&event.data[1..]),
+                target::evt::END => {
+                    received.extend_from_slice(&event.data[1..]);
+                    return Ok(received);
+                }
+                target::evt::OVERFLOW => {
+                    return Err(RequestError::Protocol("spi target receive overflow"));
+                }
+                _ => return Err(RequestError::Protocol("unknown spi target event")),
+            }
+        }
+    }
+
+    /// Stream of the bytes received in each transaction.
+    pub fn transactions(&self) -> impl Stream<Item = Result<Vec<u8>, RequestError>> + '_ {
+        stream::unfold(self, |this| async move {
+            Some((this.next_transaction().await, this))
+        })
+    }
+}
+
 pub struct SckPin {
     #[allow(unused)]
     resource: Resource,
@@ -126,16 +585,185 @@ pub struct SdiPin {
 
 resource_mode!(SdiPin, SdiPinBuilder, sdi_pin::PROTOCOL);
 
+/// Chip select driven by the SPI controller, which enforces setup and hold
+/// times around transfers.
+pub struct CsPin {
+    resource: Resource,
+    active_high: bool,
+}
+
+pub struct CsPinBuilder {
+    resource: Resource,
+    mode: u8,
+    config: cs_pin::Config,
+    setup: Duration,
+    hold: Duration,
+}
+
+impl ResourceMode for CsPin {
+    const PROTOCOL: u16 = cs_pin::PROTOCOL;
+    type Builder = CsPinBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        CsPinBuilder {
+            resource,
+            mode,
+            config: cs_pin::Config::default(),
+            setup: Duration::ZERO,
+            hold: Duration::ZERO,
+        }
+    }
+}
+
+impl CsPinBuilder {
+    /// Drive chip select high when asserted instead of low.
+    pub fn active_high(mut self) -> Self {
+        self.config.flags = self.config.flags.union(cs_pin::ConfigFlags::ACTIVE_HIGH);
+        self
+    }
+
+    /// Minimum delay from asserting chip select to the first SCK edge.
+    pub fn setup(mut self, delay: Duration) -> Self {
+        self.setup = delay;
+        self
+    }
+
+    /// Minimum delay from the last SCK edge to releasing chip select.
+    pub fn hold(mut self, delay: Duration) -> Self {
+        self.hold = delay;
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<CsPin, crate::Error> {
+        let desc: cs_pin::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let active_high = self.config.flags.contains(cs_pin::ConfigFlags::ACTIVE_HIGH);
+        if active_high && !desc.flags.contains(cs_pin::ModeFlags::ACTIVE_HIGH) {
+            Err("active-high chip select not supported")?
+        }
+
+        let max_delay = desc.max_delay.get() as u128;
+        let (setup, hold) = (self.setup.as_nanos(), self.hold.as_nanos());
+        if setup > max_delay || hold > max_delay {
+            Err("chip select delay not supported")?
+        }
+        self.config.setup.set(setup as u16);
+        self.config.hold.set(hold as u16);
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        Ok(CsPin {
+            resource,
+            active_high,
+        })
+    }
+}
+
+impl CsPin {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    pub fn active_high(&self) -> bool {
+        self.active_high
+    }
+
+    pub fn cmd_assert(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, cs_pin::cmd::ASSERT, (), ())
+    }
+
+    pub fn cmd_release(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, cs_pin::cmd::RELEASE, (), ())
+    }
+}
+
+/// Chip select of a SPI device.
+pub enum ChipSelect {
+    /// GPIO output toggled by separate commands.
+    Gpio { pin: Gpio, active_high: bool },
+
+    /// Pin driven by the SPI controller.
+    Hardware(CsPin),
+}
+
+impl From<Gpio> for ChipSelect {
+    /// Use an active-low GPIO output.
+    fn from(pin: Gpio) -> Self {
+        ChipSelect::Gpio {
+            pin,
+            active_high: false,
+        }
+    }
+}
+
+impl From<CsPin> for ChipSelect {
+    fn from(pin: CsPin) -> Self {
+        ChipSelect::Hardware(pin)
+    }
+}
+
+impl ChipSelect {
+    /// Configure `resource` as a hardware chip select if it supports the
+    /// mode, and otherwise as an active-low GPIO output driven high.
+    pub async fn enable(resource: Resource) -> Result<Self, crate::Error> {
+        if resource.descriptor().find_mode(cs_pin::PROTOCOL).is_some() {
+            Ok(resource.as_mode::<CsPin>()?.enable().await?.into())
+        } else {
+            let pin = resource.as_mode::<Gpio>()?.enable().await?;
+            pin.high()
+                .await
+                .map_err(|e| crate::Error::new("failed to set chip select", e))?;
+            Ok(pin.into())
+        }
+    }
+
+    fn interface(&self) -> &Arc<Interface> {
+        match self {
+            ChipSelect::Gpio { pin, .. } => &pin.resource.interface,
+            ChipSelect::Hardware(pin) => &pin.resource.interface,
+        }
+    }
+
+    pub fn active_high(&self) -> bool {
+        match self {
+            ChipSelect::Gpio { active_high, .. } => *active_high,
+            ChipSelect::Hardware(pin) => pin.active_high,
+        }
+    }
+
+    pub fn cmd_assert(&self) -> Command<(), ()> {
+        match self {
+            ChipSelect::Gpio { pin, active_high } => pin.cmd_write(*active_high),
+            ChipSelect::Hardware(pin) => pin.cmd_assert(),
+        }
+    }
+
+    pub fn cmd_release(&self) -> Command<(), ()> {
+        match self {
+            ChipSelect::Gpio { pin, active_high } => pin.cmd_write(!*active_high),
+            ChipSelect::Hardware(pin) => pin.cmd_release(),
+        }
+    }
+}
+
 pub struct Device<C = Arc<Controller>> {
-    controller: C,
-    chip_select: Gpio,
+    pub(crate) controller: C,
+    pub(crate) chip_select: ChipSelect,
 }
 
 impl<C: AsRef<Controller>> Device<C> {
-    pub fn new(controller: C, chip_select: Gpio) -> Self {
+    /// Create a device selected by a GPIO output or hardware chip select pin.
+    pub fn new(controller: C, chip_select: impl Into<ChipSelect>) -> Self {
+        let chip_select = chip_select.into();
         assert!(Arc::ptr_eq(
             &controller.as_ref().resource.interface,
-            &chip_select.resource.interface
+            chip_select.interface()
         ));
         Self {
             controller,
@@ -151,58 +779,368 @@ impl<C: AsRef<Controller>> embedded_hal_async::spi::ErrorType for Device<C> {
 impl<C: AsRef<Controller>> embedded_hal_async::spi::SpiDevice for Device<C> {
     async fn transaction(
         &mut self,
-        operations: &mut [embedded_hal_async::spi::Operation<'_, u8>],
+        operations: &mut [Operation<'_, u8>],
     ) -> Result<(), Self::Error> {
+        self.run_transactions(&mut [operations]).await
+    }
+}
+
+impl<C: AsRef<Controller>> Device<C> {
+    /// Run several transactions in a single command queue, asserting chip
+    /// select around each.
+    pub(crate) async fn run_transactions(
+        &self,
+        transactions: &mut [&mut [Operation<'_, u8>]],
+    ) -> Result<(), Error> {
         let controller = self.controller.as_ref();
+        controller.check_byte_words()?;
         let mut queue = controller.resource.interface.queue();
 
-        let zeros = [0; 255];
+        for operations in transactions.iter_mut() {
+            queue.push(self.chip_select.cmd_assert()).await;
+            push_operations(controller, &mut queue, operations, 255).await;
+            queue.push(self.chip_select.cmd_release()).await;
+        }
+        queue.finish().await?;
+
+        Ok(())
+    }
+}
+
+/// Queue the commands for the operations of a device transaction, splitting
+/// data into commands of at most `chunk` bytes.
+async fn push_operations<'a>(
+    controller: &Controller,
+    queue: &mut CommandQueue<'a>,
+    operations: &'a mut [Operation<'_, u8>],
+    chunk: usize,
+) {
+    const ZEROS: [u8; 255] = [0; 255];
 
-        queue.push(self.chip_select.cmd_write(false)).await;
+    for op in operations.iter_mut() {
+        match op {
+            Operation::DelayNs(ns) => {
+                queue
+                    .push(cmd_delay(ns.div_ceil(1000).try_into().unwrap_or(u16::MAX)))
+                    .await;
+            }
 
-        for op in operations.into_iter() {
-            match op {
-                embedded_hal_async::spi::Operation::DelayNs(ns) => {
+            Operation::Read(buf) => {
+                for chunk in buf.chunks_mut(chunk) {
                     queue
-                        .push(cmd_delay(ns.div_ceil(1000).try_into().unwrap_or(u16::MAX)))
+                        .push_read(controller.cmd_transfer(&ZEROS[..chunk.len()]), chunk)
                         .await;
                 }
+            }
 
-                embedded_hal_async::spi::Operation::Read(buf) => {
-                    for chunk in buf.chunks_mut(255) {
-                        queue
-                            .push_read(controller.cmd_transfer(&zeros[..chunk.len()]), chunk)
-                            .await;
-                    }
+            Operation::Write(buf) => {
+                for chunk in buf.chunks(chunk) {
+                    queue.push(controller.cmd_transfer(chunk)).await;
                 }
+            }
 
-                embedded_hal_async::spi::Operation::Write(buf) => {
-                    for chunk in buf.chunks(255) {
-                        queue.push(controller.cmd_transfer(chunk)).await;
-                    }
+            Operation::Transfer(rx, tx) => {
+                assert_eq!(rx.len(), tx.len());
+                for (rx_chunk, tx_chunk) in rx.chunks_mut(chunk).zip(tx.chunks(chunk)) {
+                    queue
+                        .push_read(controller.cmd_transfer(tx_chunk), rx_chunk)
+                        .await;
                 }
+            }
 
-                embedded_hal_async::spi::Operation::Transfer(rx, tx) => {
-                    assert_eq!(rx.len(), tx.len());
-                    for (rx_chunk, tx_chunk) in rx.chunks_mut(255).zip(tx.chunks(255)) {
-                        queue
-                            .push_read(controller.cmd_transfer(tx_chunk), rx_chunk)
-                            .await;
-                    }
+            Operation::TransferInPlace(buf) => {
+                for chunk in buf.chunks_mut(chunk) {
+                    queue
+                        .push_read_in_place(chunk, |b| controller.cmd_transfer(b))
+                        .await;
                 }
+            }
+        }
+    }
+}
 
-                embedded_hal_async::spi::Operation::TransferInPlace(buf) => {
-                    for chunk in buf.chunks_mut(255) {
-                        queue
-                            .push_read_in_place(chunk, |b| controller.cmd_transfer(b))
-                            .await;
-                    }
+/// SPI settings of a device on a [`SpiBus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceConfig {
+    /// SPI mode 0-3.
+    pub mode: u8,
+    /// Maximum SCK frequency in Hz.
+    pub frequency: u32,
+    pub lsb_first: bool,
+    /// Chip select is asserted high instead of low. For a hardware chip
+    /// select, this must match the pin's configuration.
+    pub cs_active_high: bool,
+    /// Bits per word. Words of more than 8 bits are transferred with the
+    /// [`SpiDevice<u16>`](embedded_hal_async::spi::SpiDevice) or
+    /// `SpiDevice<u32>` implementation.
+    pub word_bits: u8,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            mode: 0,
+            frequency: 1_000_000,
+            lsb_first: false,
+            cs_active_high: false,
+            word_bits: 8,
+        }
+    }
+}
+
+/// SPI controller shared by several devices, each with its own chip select
+/// and settings.
+///
+/// The controller is reconfigured only when a transaction's settings differ
+/// from those of the previous transaction. Each transaction holds the bus
+/// until it completes, so transactions from concurrent tasks are not
+/// interleaved.
+pub struct SpiBus {
+    state: async_lock::Mutex<BusState>,
+}
+
+/// Flags, clock divider and bits per word the controller is configured with.
+type BusSettings = (controller::ConfigFlags, u32, u8);
+
+struct BusState {
+    controller: Controller,
+    active: Option<BusSettings>,
+}
+
+impl SpiBus {
+    pub fn new(controller: Controller) -> Arc<Self> {
+        Arc::new(Self {
+            state: async_lock::Mutex::new(BusState {
+                controller,
+                active: None,
+            }),
+        })
+    }
+
+    /// Add a device selected by `chip_select`. A GPIO chip select is driven
+    /// to its inactive level.
+    pub async fn device(
+        self: &Arc<Self>,
+        chip_select: impl Into<ChipSelect>,
+        config: DeviceConfig,
+    ) -> Result<BusDevice, crate::Error> {
+        use controller::{ConfigFlags, ModeFlags};
+
+        let state = self.state.lock().await;
+        let resource = &state.controller.resource;
+        let mut chip_select = chip_select.into();
+        assert!(Arc::ptr_eq(&resource.interface, chip_select.interface()));
+
+        let mode = resource.mode_id.expect("controller is configured");
+        let desc: controller::DescribeMode =
+            resource.mode_descriptor(mode).ok_or("mode not found")?;
+
+        let mode_flag = match config.mode {
+            0 => ModeFlags::MODE0,
+            1 => ModeFlags::MODE1,
+            2 => ModeFlags::MODE2,
+            3 => ModeFlags::MODE3,
+            _ => Err("invalid SPI mode")?,
+        };
+        if !desc.flags.contains(mode_flag) {
+            Err("SPI mode not supported")?
+        }
+
+        let mut flags = ConfigFlags::for_mode(config.mode);
+        let bit_order = if config.lsb_first {
+            flags = flags.union(ConfigFlags::LSB_FIRST);
+            ModeFlags::LSB_FIRST
+        } else {
+            ModeFlags::MSB_FIRST
+        };
+        if !desc.flags.contains(bit_order) {
+            Err("bit order not supported")?
+        }
+
+        let base_clock = desc.base_clock.get();
+        let clock_div = base_clock.div_ceil(config.frequency.max(1)).max(1);
+        if clock_div > desc.max_div.get() {
+            Err("frequency too low for clock divider")?
+        }
+
+        if !word_bits_range(&desc).contains(&config.word_bits) {
+            Err("word size not supported")?
+        }
+        drop(state);
+
+        match &mut chip_select {
+            ChipSelect::Gpio { pin, active_high } => {
+                *active_high = config.cs_active_high;
+                pin.write(!config.cs_active_high)
+                    .await
+                    .map_err(|e| crate::Error::new("failed to set chip select", e))?;
+            }
+            ChipSelect::Hardware(pin) => {
+                if pin.active_high != config.cs_active_high {
+                    Err("chip select polarity does not match pin configuration")?
                 }
             }
         }
-        queue.push(self.chip_select.cmd_write(true)).await;
+
+        Ok(BusDevice {
+            bus: self.clone(),
+            chip_select,
+            flags,
+            clock_div,
+            word_bits: config.word_bits,
+            frequency: base_clock / clock_div,
+        })
+    }
+}
+
+/// Device on a [`SpiBus`].
+pub struct BusDevice {
+    bus: Arc<SpiBus>,
+    chip_select: ChipSelect,
+    flags: controller::ConfigFlags,
+    clock_div: u32,
+    word_bits: u8,
+    frequency: u32,
+}
+
+impl BusDevice {
+    /// Actual SCK frequency in Hz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Bits per word.
+    pub fn word_size(&self) -> u8 {
+        self.word_bits
+    }
+
+    /// Run several transactions of 8-bit words in a single command queue.
+    pub(crate) async fn run_transactions(
+        &self,
+        transactions: &mut [&mut [Operation<'_, u8>]],
+    ) -> Result<(), Error> {
+        if self.word_bits > 8 {
+            return Err(Error::WordSize);
+        }
+        self.run(transactions, 255).await
+    }
+
+    /// Run transactions on the bus in a single command queue, configuring the
+    /// controller for this device if necessary. Data is split into commands
+    /// of at most `chunk` bytes.
+    async fn run(
+        &self,
+        transactions: &mut [&mut [Operation<'_, u8>]],
+        chunk: usize,
+    ) -> Result<(), Error> {
+        let mut guard = self.bus.state.lock().await;
+        let state = &mut *guard;
+
+        let settings = (self.flags, self.clock_div, self.word_bits);
+        if state.active != Some(settings) {
+            let resource = &mut state.controller.resource;
+            let mode = resource.mode_id.expect("controller is configured");
+            let config = controller::Config {
+                flags: self.flags,
+                clock_div: self.clock_div.into(),
+                word_bits: self.word_bits,
+            };
+            state.active = None;
+            resource
+                .configure(mode, config.as_bytes())
+                .await
+                .map_err(Error::Configure)?;
+            state.controller.word_bits = self.word_bits;
+            state.active = Some(settings);
+        }
+
+        let controller = &state.controller;
+        let mut queue = controller.resource.interface.queue();
+
+        for operations in transactions.iter_mut() {
+            queue.push(self.chip_select.cmd_assert()).await;
+            push_operations(controller, &mut queue, operations, chunk).await;
+            queue.push(self.chip_select.cmd_release()).await;
+        }
         queue.finish().await?;
 
         Ok(())
     }
 }
+
+impl embedded_hal_async::spi::ErrorType for BusDevice {
+    type Error = Error;
+}
+
+impl embedded_hal_async::spi::SpiDevice for BusDevice {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        self.run_transactions(&mut [operations]).await
+    }
+}
+
+/// Implement `SpiDevice` for words of more than 8 bits, packed little-endian
+/// into the command data.
+macro_rules! bus_device_word {
+    ($word:ty, $bits:pat) => {
+        impl embedded_hal_async::spi::SpiDevice<$word> for BusDevice {
+            async fn transaction(
+                &mut self,
+                operations: &mut [Operation<'_, $word>],
+            ) -> Result<(), Self::Error> {
+                const N: usize = size_of::<$word>();
+                if !matches!(self.word_bits, $bits) {
+                    return Err(Error::WordSize);
+                }
+
+                let pack = |words: &[$word]| -> Vec<u8> {
+                    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+                };
+                let mut bufs: Vec<Vec<u8>> = operations
+                    .iter()
+                    .map(|op| match op {
+                        Operation::Read(w) => vec![0; w.len() * N],
+                        Operation::Write(w) => pack(w),
+                        Operation::Transfer(r, w) => {
+                            assert_eq!(r.len(), w.len());
+                            pack(w)
+                        }
+                        Operation::TransferInPlace(w) => pack(w),
+                        Operation::DelayNs(_) => Vec::new(),
+                    })
+                    .collect();
+
+                let mut byte_ops: Vec<Operation<'_, u8>> = operations
+                    .iter()
+                    .zip(bufs.iter_mut())
+                    .map(|(op, buf)| match op {
+                        Operation::Read(_) => Operation::Read(buf),
+                        Operation::Write(_) => Operation::Write(buf),
+                        Operation::DelayNs(ns) => Operation::DelayNs(*ns),
+                        _ => Operation::TransferInPlace(buf),
+                    })
+                    .collect();
+                self.run(&mut [&mut byte_ops], 255 / N * N).await?;
+                drop(byte_ops);
+
+                for (op, buf) in operations.iter_mut().zip(&bufs) {
+                    let dest = match op {
+                        Operation::Read(w) => w,
+                        Operation::Transfer(r, _) => r,
+                        Operation::TransferInPlace(w) => w,
+                        _ => continue,
+                    };
+                    for (w, b) in dest.iter_mut().zip(buf.chunks_exact(N)) {
+                        *w = <$word>::from_le_bytes(b.try_into().unwrap());
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+bus_device_word!(u16, 9..=16);
+bus_device_word!(u32, 17..=32);
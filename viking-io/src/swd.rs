@@ -0,0 +1,387 @@
+use thiserror::Error;
+use zerocopy::IntoBytes;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, Raw, SliceResponse},
+};
+use viking_protocol::protocol::swd as protocol;
+
+/// Debug port register addresses.
+pub mod dp {
+    pub const DPIDR: u8 = 0x0;
+    pub const ABORT: u8 = 0x0;
+    pub const CTRL_STAT: u8 = 0x4;
+    pub const SELECT: u8 = 0x8;
+    pub const RDBUFF: u8 = 0xC;
+
+    pub const ABORT_CLEAR_ALL: u32 = 0x1E;
+    pub const CTRL_STAT_CDBGPWRUPREQ: u32 = 1 << 28;
+    pub const CTRL_STAT_CDBGPWRUPACK: u32 = 1 << 29;
+    pub const CTRL_STAT_CSYSPWRUPREQ: u32 = 1 << 30;
+    pub const CTRL_STAT_CSYSPWRUPACK: u32 = 1 << 31;
+}
+
+/// MEM-AP register addresses.
+pub mod mem_ap {
+    pub const CSW: u8 = 0x00;
+    pub const TAR: u8 = 0x04;
+    pub const DRW: u8 = 0x0C;
+    pub const IDR: u8 = 0xFC;
+
+    /// 32-bit access size with auto-increment disabled.
+    pub const CSW_WORD: u32 = 0x2300_0002;
+}
+
+/// Cortex-M Debug Halting Control and Status Register.
+pub const DHCSR: u32 = 0xE000_EDF0;
+const DHCSR_KEY: u32 = 0xA05F << 16;
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+
+/// 58 cycles with SWDIO high followed by 6 idle cycles.
+const LINE_RESET: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x03];
+
+/// Number of memory words accessed per batch.
+const CHUNK_WORDS: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("target responded WAIT")]
+    Wait,
+
+    #[error("target responded FAULT")]
+    Fault,
+
+    #[error("no response from target")]
+    NoResponse,
+
+    #[error("read data parity error")]
+    Parity,
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+fn check_ack(ack: u8) -> Result<(), Error> {
+    if ack & protocol::ack::PARITY_ERROR != 0 {
+        return Err(Error::Parity);
+    }
+    match ack & protocol::ack::MASK {
+        protocol::ack::OK => Ok(()),
+        protocol::ack::WAIT => Err(Error::Wait),
+        protocol::ack::FAULT => Err(Error::Fault),
+        _ => Err(Error::NoResponse),
+    }
+}
+
+fn parse_read(res: &[u8]) -> Result<u32, Error> {
+    check_ack(res[0])?;
+    Ok(u32::from_le_bytes(res[1..5].try_into().unwrap()))
+}
+
+/// Port and register address of an SWD transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Port {
+    Dp(u8),
+    Ap(u8),
+}
+
+impl Port {
+    fn request(self) -> u8 {
+        match self {
+            Port::Dp(addr) => addr & protocol::request::ADDR_MASK,
+            Port::Ap(addr) => addr & protocol::request::ADDR_MASK | protocol::request::APNDP,
+        }
+    }
+}
+
+/// ARM Serial Wire Debug host.
+pub struct Swd {
+    resource: Resource,
+}
+
+pub struct SwdBuilder {
+    resource: Resource,
+    mode: u8,
+    config: protocol::Config,
+    frequency: Option<u32>,
+}
+
+impl ResourceMode for Swd {
+    const PROTOCOL: u16 = protocol::PROTOCOL;
+    type Builder = SwdBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        SwdBuilder {
+            resource,
+            mode,
+            config: protocol::Config::default(),
+            frequency: None,
+        }
+    }
+}
+
+impl SwdBuilder {
+    /// Maximum SWCLK frequency in Hz. Defaults to the fastest supported.
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.frequency = Some(hz);
+        self
+    }
+
+    /// Turnaround period in clock cycles, 1 to 4. Defaults to 1.
+    pub fn turnaround(mut self, cycles: u8) -> Self {
+        self.config.turnaround = cycles;
+        self
+    }
+
+    /// Idle cycles inserted after each transfer. Defaults to 0.
+    pub fn idle_cycles(mut self, cycles: u8) -> Self {
+        self.config.idle_cycles = cycles;
+        self
+    }
+
+    /// Number of times the device retries a transfer that was answered with
+    /// WAIT before reporting it. Defaults to 100.
+    pub fn wait_retries(mut self, retries: u16) -> Self {
+        self.config.wait_retries.set(retries);
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<Swd, crate::Error> {
+        let desc: protocol::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        if !(1..=4).contains(&self.config.turnaround) {
+            Err("turnaround must be 1 to 4 cycles")?
+        }
+
+        let base_clock = desc.base_clock.get();
+        let div = match self.frequency {
+            Some(hz) if hz > 0 => base_clock.div_ceil(hz).max(1),
+            _ => 1,
+        };
+        if div > desc.max_div.get() {
+            Err("frequency too low")?
+        }
+        self.config.clock_div.set(div);
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+        Ok(Swd { resource })
+    }
+}
+
+impl Swd {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Command clocking out `bits` bits of `data` on SWDIO, LSB first.
+    pub fn cmd_sequence<'a>(&self, bits: u8, data: &'a [u8]) -> Command<(u8, Raw<'a>), ()> {
+        assert_eq!(data.len(), (bits as usize).div_ceil(8));
+        Command::new(
+            self.resource.id,
+            protocol::cmd::SEQUENCE,
+            (bits, Raw(data)),
+            (),
+        )
+    }
+
+    /// Command reading a register, returning `<ack> <data:u32>`.
+    pub fn cmd_read(&self, port: Port) -> Command<u8, SliceResponse> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::READ,
+            port.request(),
+            SliceResponse::new(5),
+        )
+    }
+
+    /// Command writing a register, returning `<ack>`.
+    pub fn cmd_write(&self, port: Port, value: u32) -> Command<(u8, u32), SliceResponse> {
+        Command::new(
+            self.resource.id,
+            protocol::cmd::WRITE,
+            (port.request(), value),
+            SliceResponse::new(1),
+        )
+    }
+
+    /// Clock out more than 50 cycles with SWDIO high followed by idle
+    /// cycles.
+    pub async fn line_reset(&self) -> Result<(), RequestError> {
+        let cmd = self.cmd_sequence(64, &LINE_RESET);
+        self.resource.interface.run(cmd).await
+    }
+
+    /// Switch a SWJ-DP from JTAG to SWD using the JTAG-to-SWD sequence,
+    /// followed by a line reset.
+    pub async fn jtag_to_swd(&self) -> Result<(), RequestError> {
+        let reset = [0xff; 7];
+        let mut queue = self.resource.interface.queue();
+        queue.push(self.cmd_sequence(56, &reset)).await;
+        queue
+            .push(self.cmd_sequence(16, &0xE79Eu16.to_le_bytes()))
+            .await;
+        queue.push(self.cmd_sequence(64, &LINE_RESET)).await;
+        queue.finish().await
+    }
+
+    pub async fn read(&self, port: Port) -> Result<u32, Error> {
+        let mut batch = self.resource.interface.batch();
+        let read = batch.push(self.cmd_read(port));
+        let res = batch.run().await?;
+        parse_read(res.get(read)?)
+    }
+
+    pub async fn write(&self, port: Port, value: u32) -> Result<(), Error> {
+        let mut batch = self.resource.interface.batch();
+        let write = batch.push(self.cmd_write(port, value));
+        let res = batch.run().await?;
+        check_ack(res.get(write)?[0])
+    }
+
+    pub async fn read_dp(&self, addr: u8) -> Result<u32, Error> {
+        self.read(Port::Dp(addr)).await
+    }
+
+    pub async fn write_dp(&self, addr: u8, value: u32) -> Result<(), Error> {
+        self.write(Port::Dp(addr), value).await
+    }
+
+    /// Read register `addr` of access port `ap`, selecting the AP and bank
+    /// and reading the posted result from RDBUFF in a single batch.
+    pub async fn read_ap(&self, ap: u8, addr: u8) -> Result<u32, Error> {
+        let mut batch = self.resource.interface.batch();
+        let select = batch.push(self.cmd_write(Port::Dp(dp::SELECT), select(ap, addr)));
+        let read = batch.push(self.cmd_read(Port::Ap(addr)));
+        let rdbuff = batch.push(self.cmd_read(Port::Dp(dp::RDBUFF)));
+        let res = batch.run().await?;
+        check_ack(res.get(select)?[0])?;
+        parse_read(res.get(read)?)?;
+        parse_read(res.get(rdbuff)?)
+    }
+
+    /// Write register `addr` of access port `ap`.
+    pub async fn write_ap(&self, ap: u8, addr: u8, value: u32) -> Result<(), Error> {
+        let mut batch = self.resource.interface.batch();
+        let select = batch.push(self.cmd_write(Port::Dp(dp::SELECT), select(ap, addr)));
+        let write = batch.push(self.cmd_write(Port::Ap(addr), value));
+        let res = batch.run().await?;
+        check_ack(res.get(select)?[0])?;
+        check_ack(res.get(write)?[0])
+    }
+
+    /// Switch to SWD, reset the line, and read the debug port ID register.
+    pub async fn connect(&self) -> Result<u32, Error> {
+        self.jtag_to_swd().await?;
+        let idcode = self.read_dp(dp::DPIDR).await?;
+        self.write_dp(dp::ABORT, dp::ABORT_CLEAR_ALL).await?;
+        Ok(idcode)
+    }
+
+    /// Request debug and system power-up and wait for acknowledgement.
+    pub async fn power_up(&self) -> Result<(), Error> {
+        let req = dp::CTRL_STAT_CDBGPWRUPREQ | dp::CTRL_STAT_CSYSPWRUPREQ;
+        let ack = dp::CTRL_STAT_CDBGPWRUPACK | dp::CTRL_STAT_CSYSPWRUPACK;
+        self.write_dp(dp::CTRL_STAT, req).await?;
+        for _ in 0..100 {
+            if self.read_dp(dp::CTRL_STAT).await? & ack == ack {
+                return Ok(());
+            }
+        }
+        Err(Error::NoResponse)
+    }
+
+    /// Read 32-bit words from target memory through MEM-AP `ap`.
+    pub async fn read_memory(&self, ap: u8, addr: u32, words: &mut [u32]) -> Result<(), Error> {
+        for (i, chunk) in words.chunks_mut(CHUNK_WORDS).enumerate() {
+            let base = addr + (i * CHUNK_WORDS * 4) as u32;
+
+            let mut batch = self.resource.interface.batch();
+            let mut writes = vec![
+                batch.push(self.cmd_write(Port::Dp(dp::SELECT), select(ap, 0))),
+                batch.push(self.cmd_write(Port::Ap(mem_ap::CSW), mem_ap::CSW_WORD)),
+            ];
+            let mut reads = Vec::with_capacity(chunk.len());
+            for j in 0..chunk.len() {
+                let tar = base + j as u32 * 4;
+                writes.push(batch.push(self.cmd_write(Port::Ap(mem_ap::TAR), tar)));
+                reads.push(batch.push(self.cmd_read(Port::Ap(mem_ap::DRW))));
+            }
+            let rdbuff = batch.push(self.cmd_read(Port::Dp(dp::RDBUFF)));
+            let res = batch.run().await?;
+
+            for write in writes {
+                check_ack(res.get(write)?[0])?;
+            }
+            // AP reads are posted: each returns the result of the previous one
+            let mut values = Vec::with_capacity(chunk.len() + 1);
+            for read in reads {
+                values.push(parse_read(res.get(read)?)?);
+            }
+            values.push(parse_read(res.get(rdbuff)?)?);
+            chunk.copy_from_slice(&values[1..]);
+        }
+        Ok(())
+    }
+
+    /// Write 32-bit words to target memory through MEM-AP `ap`.
+    pub async fn write_memory(&self, ap: u8, addr: u32, words: &[u32]) -> Result<(), Error> {
+        for (i, chunk) in words.chunks(CHUNK_WORDS).enumerate() {
+            let base = addr + (i * CHUNK_WORDS * 4) as u32;
+
+            let mut batch = self.resource.interface.batch();
+            let mut writes = vec![
+                batch.push(self.cmd_write(Port::Dp(dp::SELECT), select(ap, 0))),
+                batch.push(self.cmd_write(Port::Ap(mem_ap::CSW), mem_ap::CSW_WORD)),
+            ];
+            for (j, &word) in chunk.iter().enumerate() {
+                let tar = base + j as u32 * 4;
+                writes.push(batch.push(self.cmd_write(Port::Ap(mem_ap::TAR), tar)));
+                writes.push(batch.push(self.cmd_write(Port::Ap(mem_ap::DRW), word)));
+            }
+            let rdbuff = batch.push(self.cmd_read(Port::Dp(dp::RDBUFF)));
+            let res = batch.run().await?;
+
+            for write in writes {
+                check_ack(res.get(write)?[0])?;
+            }
+            parse_read(res.get(rdbuff)?)?;
+        }
+        Ok(())
+    }
+
+    pub async fn read_memory32(&self, ap: u8, addr: u32) -> Result<u32, Error> {
+        let mut word = [0];
+        self.read_memory(ap, addr, &mut word).await?;
+        Ok(word[0])
+    }
+
+    pub async fn write_memory32(&self, ap: u8, addr: u32, value: u32) -> Result<(), Error> {
+        self.write_memory(ap, addr, &[value]).await
+    }
+
+    /// Halt a Cortex-M core through MEM-AP `ap`.
+    pub async fn halt(&self, ap: u8) -> Result<(), Error> {
+        let value = DHCSR_KEY | DHCSR_C_DEBUGEN | DHCSR_C_HALT;
+        self.write_memory32(ap, DHCSR, value).await
+    }
+
+    /// Resume a halted Cortex-M core through MEM-AP `ap`, leaving debug
+    /// enabled.
+    pub async fn resume(&self, ap: u8) -> Result<(), Error> {
+        self.write_memory32(ap, DHCSR, DHCSR_KEY | DHCSR_C_DEBUGEN)
+            .await
+    }
+}
+
+/// Value of DP SELECT for access port `ap` and the register bank of `addr`.
+fn select(ap: u8, addr: u8) -> u32 {
+    (ap as u32) << 24 | (addr as u32 & 0xF0)
+}
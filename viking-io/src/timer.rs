@@ -0,0 +1,881 @@
+use std::{io, time::Duration};
+
+use thiserror::Error;
+use zerocopy::IntoBytes;
+use zerocopy::little_endian::U32;
+
+use crate::{
+    RequestError, Resource, ResourceMode,
+    command::{Command, PayloadPattern, ScalarResponse, StatusSliceResponse},
+};
+use viking_protocol::protocol::timer::{capture, counter, quadrature, waveform};
+
+/// Maximum number of entries requested by a single capture `READ` command.
+const CAPTURE_READ_LEN: u8 = 64;
+
+/// Maximum number of entries sent by a single waveform `LOAD` command.
+const WAVEFORM_LOAD_LEN: usize = 40;
+
+fn parse_entries(data: &[u8]) -> impl Iterator<Item = (bool, u32)> + '_ {
+    data.chunks_exact(4).map(|b| {
+        let entry = u32::from_le_bytes(b.try_into().unwrap());
+        (
+            entry & capture::ENTRY_LEVEL != 0,
+            entry & capture::ENTRY_TIMESTAMP,
+        )
+    })
+}
+
+/// Records timestamps of edges on a pin.
+pub struct Capture {
+    resource: Resource,
+    timestamp_clock: u32,
+}
+
+pub struct CaptureBuilder {
+    resource: Resource,
+    mode: u8,
+    config: capture::Config,
+}
+
+impl ResourceMode for Capture {
+    const PROTOCOL: u16 = capture::PROTOCOL;
+    type Builder = CaptureBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        CaptureBuilder {
+            resource,
+            mode,
+            config: capture::Config::default(),
+        }
+    }
+}
+
+impl CaptureBuilder {
+    fn set_edges(mut self, edges: capture::ConfigFlags) -> Self {
+        let events = self.config.flags.contains(capture::ConfigFlags::EVENTS);
+        self.config.flags = edges;
+        if events {
+            self.config.flags = self.config.flags.union(capture::ConfigFlags::EVENTS);
+        }
+        self
+    }
+
+    /// Capture rising edges only.
+    pub fn rising(self) -> Self {
+        self.set_edges(capture::ConfigFlags::RISING)
+    }
+
+    /// Capture falling edges only.
+    pub fn falling(self) -> Self {
+        self.set_edges(capture::ConfigFlags::FALLING)
+    }
+
+    /// Capture both rising and falling edges (default).
+    pub fn both(self) -> Self {
+        self.set_edges(capture::ConfigFlags::RISING.union(capture::ConfigFlags::FALLING))
+    }
+
+    /// Deliver captured edges as events instead of buffering them until read.
+    pub fn events(mut self) -> Self {
+        self.config.flags = self.config.flags.union(capture::ConfigFlags::EVENTS);
+        self
+    }
+
+    pub async fn enable(self) -> Result<Capture, crate::Error> {
+        use capture::{ConfigFlags, ModeFlags};
+
+        let desc: capture::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let rising = self.config.flags.contains(ConfigFlags::RISING);
+        let falling = self.config.flags.contains(ConfigFlags::FALLING);
+        let supported = match (rising, falling) {
+            (true, true) => desc.flags.contains(ModeFlags::BOTH),
+            (true, false) => desc.flags.contains(ModeFlags::RISING),
+            (false, true) => desc.flags.contains(ModeFlags::FALLING),
+            (false, false) => false,
+        };
+        if !supported {
+            Err("edge selection not supported")?
+        }
+
+        let events = self.config.flags.contains(ConfigFlags::EVENTS);
+        if events && !desc.flags.contains(ModeFlags::EVENTS) {
+            Err("capture events not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        if events {
+            resource
+                .subscribe_events(|evt, data| match evt {
+                    capture::evt::DATA => Some(1 + *data.first()? as usize * 4),
+                    _ => Some(4),
+                })
+                .await;
+        }
+
+        Ok(Capture {
+            resource,
+            timestamp_clock: desc.timestamp_clock.get(),
+        })
+    }
+}
+
+impl Capture {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Frequency in Hz of the counter used for timestamps.
+    pub fn timestamp_clock(&self) -> u32 {
+        self.timestamp_clock
+    }
+
+    pub fn cmd_start(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, capture::cmd::START, (), ())
+    }
+
+    /// Clear the capture buffer and start recording edges.
+    pub async fn start(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_start()).await
+    }
+
+    pub fn cmd_stop(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, capture::cmd::STOP, (), ())
+    }
+
+    pub async fn stop(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_stop()).await
+    }
+
+    pub fn cmd_sample(&self) -> Command<(), ScalarResponse<u32>> {
+        Command::new(
+            self.resource.id,
+            capture::cmd::SAMPLE,
+            (),
+            ScalarResponse::new(),
+        )
+    }
+
+    /// Read the current pin level and timestamp.
+    pub async fn sample(&self) -> Result<(bool, u32), RequestError> {
+        let entry = self.resource.interface.run(self.cmd_sample()).await?;
+        Ok((
+            entry & capture::ENTRY_LEVEL != 0,
+            entry & capture::ENTRY_TIMESTAMP,
+        ))
+    }
+
+    pub fn cmd_read(&self, len: u8) -> Command<u8, StatusSliceResponse> {
+        Command::new(
+            self.resource.id,
+            capture::cmd::READ,
+            len,
+            StatusSliceResponse::new(len as usize * 4),
+        )
+    }
+
+    /// Remove all edges from the capture buffer, returning the pin level
+    /// after each edge and its timestamp.
+    pub async fn read(&self) -> Result<Vec<(bool, u32)>, RequestError> {
+        let mut edges = Vec::new();
+        loop {
+            let (count, data) = self
+                .resource
+                .interface
+                .run(self.cmd_read(CAPTURE_READ_LEN))
+                .await?;
+            let count = count.min(CAPTURE_READ_LEN);
+            edges.extend(parse_entries(&data[..count as usize * 4]));
+            if count < CAPTURE_READ_LEN {
+                return Ok(edges);
+            }
+        }
+    }
+
+    /// Wait for the next batch of edges when configured for events.
+    ///
+    /// Returns an error if the device's buffer overflowed and edges were lost.
+    pub async fn next_edges(&self) -> Result<Vec<(bool, u32)>, RequestError> {
+        let event = self.resource.next_event().await?;
+        match event.evt {
+            capture::evt::DATA => Ok(parse_entries(&event.data[1..]).collect()),
+            capture::evt::OVERFLOW => Err(RequestError::Protocol("capture buffer overflow")),
+            _ => Err(RequestError::Protocol("unknown capture event")),
+        }
+    }
+
+    /// Start capturing into a new [`Trace`] beginning at the current pin level.
+    pub async fn start_trace(&self) -> Result<Trace, RequestError> {
+        let mut batch = self.resource.interface.batch();
+        let sample = batch.push(self.cmd_sample());
+        let start = batch.push(self.cmd_start());
+        let res = batch.run().await?;
+        let entry = res.get(sample)?;
+        res.get(start)?;
+        Ok(Trace::new(
+            self.timestamp_clock,
+            entry & capture::ENTRY_LEVEL != 0,
+            entry & capture::ENTRY_TIMESTAMP,
+        ))
+    }
+}
+
+/// Digital waveform reconstructed from captured edges.
+///
+/// Times are in ticks of the capture clock since the start of the trace.
+pub struct Trace {
+    clock: u32,
+    initial_level: bool,
+    last_timestamp: u32,
+    now: u64,
+    edges: Vec<(u64, bool)>,
+}
+
+impl Trace {
+    pub fn new(clock: u32, initial_level: bool, timestamp: u32) -> Self {
+        Self {
+            clock,
+            initial_level,
+            last_timestamp: timestamp & capture::ENTRY_TIMESTAMP,
+            now: 0,
+            edges: Vec::new(),
+        }
+    }
+
+    /// Append an edge, given the pin level after the edge and its 31-bit
+    /// timestamp. Edges must be less than 2^31 ticks apart.
+    pub fn push(&mut self, level: bool, timestamp: u32) {
+        let timestamp = timestamp & capture::ENTRY_TIMESTAMP;
+        let elapsed = timestamp.wrapping_sub(self.last_timestamp) & capture::ENTRY_TIMESTAMP;
+        self.last_timestamp = timestamp;
+        self.now += elapsed as u64;
+        self.edges.push((self.now, level));
+    }
+
+    pub fn extend(&mut self, edges: impl IntoIterator<Item = (bool, u32)>) {
+        for (level, timestamp) in edges {
+            self.push(level, timestamp);
+        }
+    }
+
+    /// Frequency in Hz of the tick counter.
+    pub fn clock(&self) -> u32 {
+        self.clock
+    }
+
+    pub fn initial_level(&self) -> bool {
+        self.initial_level
+    }
+
+    /// Time and level after each edge.
+    pub fn edges(&self) -> &[(u64, bool)] {
+        &self.edges
+    }
+
+    fn rising_edges(&self) -> impl Iterator<Item = u64> + '_ {
+        let levels = std::iter::once(self.initial_level).chain(self.edges.iter().map(|e| e.1));
+        levels
+            .zip(self.edges.iter())
+            .filter(|(prev, (_, level))| !*prev && *level)
+            .map(|(_, (t, _))| *t)
+    }
+
+    /// Average frequency in Hz between the first and last rising edge.
+    pub fn frequency(&self) -> Option<f64> {
+        let mut rising = self.rising_edges();
+        let first = rising.next()?;
+        let (count, last) = rising.fold((0, first), |(n, _), t| (n + 1, t));
+        if count == 0 {
+            return None;
+        }
+        let period = (last - first) as f64 / count as f64;
+        Some(self.clock as f64 / period)
+    }
+
+    /// Fraction of time the signal is high between the first and last rising edge.
+    pub fn duty_cycle(&self) -> Option<f64> {
+        let mut rising = self.rising_edges();
+        let first = rising.next()?;
+        let last = rising.last()?;
+
+        let mut high = 0;
+        let mut level = self.initial_level;
+        let mut since = 0;
+        for &(t, new_level) in &self.edges {
+            let t = t.clamp(first, last);
+            if level {
+                high += t - since.clamp(first, last);
+            }
+            level = new_level;
+            since = t;
+        }
+
+        Some(high as f64 / (last - first) as f64)
+    }
+
+    /// Write the trace in Value Change Dump format as a single wire named `name`.
+    pub fn write_vcd(&self, w: &mut impl io::Write, name: &str) -> io::Result<()> {
+        let level = |l: bool| if l { '1' } else { '0' };
+        let ns = |t: u64| (t as u128 * 1_000_000_000 / self.clock.max(1) as u128) as u64;
+
+        writeln!(w, "$timescale 1ns $end")?;
+        writeln!(w, "$scope module viking $end")?;
+        writeln!(w, "$var wire 1 ! {name} $end")?;
+        writeln!(w, "$upscope $end")?;
+        writeln!(w, "$enddefinitions $end")?;
+        writeln!(w, "#0")?;
+        writeln!(w, "$dumpvars")?;
+        writeln!(w, "{}!", level(self.initial_level))?;
+        writeln!(w, "$end")?;
+        for &(t, l) in &self.edges {
+            writeln!(w, "#{}", ns(t))?;
+            writeln!(w, "{}!", level(l))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sequence of pin levels and durations played by a [`Generator`].
+#[derive(Clone)]
+pub struct Waveform {
+    entries: Vec<waveform::Entry>,
+    loops: u16,
+}
+
+impl Default for Waveform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Waveform {
+    /// Create an empty waveform that plays once.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            loops: 1,
+        }
+    }
+
+    /// Drive the pins to the bitmap `levels` for `ticks`.
+    pub fn step(mut self, levels: u8, ticks: u32) -> Self {
+        self.push(levels, ticks);
+        self
+    }
+
+    /// Drive a single-pin waveform high for `ticks`.
+    pub fn high(self, ticks: u32) -> Self {
+        self.step(1, ticks)
+    }
+
+    /// Drive a single-pin waveform low for `ticks`.
+    pub fn low(self, ticks: u32) -> Self {
+        self.step(0, ticks)
+    }
+
+    /// Play the waveform `loops` times, or 0 to repeat until stopped.
+    pub fn repeat(mut self, loops: u16) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    pub fn push(&mut self, levels: u8, ticks: u32) {
+        self.entries.push(waveform::Entry {
+            levels,
+            ticks: U32::new(ticks),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total duration of one repetition in ticks.
+    pub fn ticks(&self) -> u64 {
+        self.entries.iter().map(|e| e.ticks.get() as u64).sum()
+    }
+}
+
+impl PayloadPattern for &[waveform::Entry] {
+    fn len(&self) -> usize {
+        1 + self.as_bytes().len()
+    }
+
+    fn bytes(&self) -> impl Iterator<Item = u8> {
+        [<[waveform::Entry]>::len(self)
+            .try_into()
+            .expect("slice must be less than 256 entries")]
+        .into_iter()
+        .chain(self.as_bytes().iter().copied())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WaveformError {
+    #[error("waveform is empty")]
+    Empty,
+
+    #[error("waveform of {0} entries does not fit in the device buffer")]
+    TooLong(usize),
+
+    #[error("entry {0} drives pins not controlled by the generator")]
+    InvalidLevels(usize),
+
+    #[error("entry {0} is shorter than the minimum duration")]
+    TooShort(usize),
+
+    #[error("{0}")]
+    Request(#[from] RequestError),
+}
+
+/// Plays timed pin sequences using a hardware timer.
+pub struct Generator {
+    resource: Resource,
+    desc: waveform::DescribeMode,
+    clock_div: u16,
+}
+
+pub struct GeneratorBuilder {
+    resource: Resource,
+    mode: u8,
+    config: waveform::Config,
+    tick_rate: Option<u32>,
+}
+
+impl ResourceMode for Generator {
+    const PROTOCOL: u16 = waveform::PROTOCOL;
+    type Builder = GeneratorBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        GeneratorBuilder {
+            resource,
+            mode,
+            config: waveform::Config::default(),
+            tick_rate: None,
+        }
+    }
+}
+
+impl GeneratorBuilder {
+    /// Divide the base clock by `div` to obtain the tick rate.
+    pub fn clock_div(mut self, div: u16) -> Self {
+        self.config.clock_div.set(div);
+        self.tick_rate = None;
+        self
+    }
+
+    /// Choose the clock divider closest to a tick rate of `hz`.
+    pub fn tick_rate(mut self, hz: u32) -> Self {
+        self.tick_rate = Some(hz);
+        self
+    }
+
+    /// Pin levels while no waveform is playing.
+    pub fn idle(mut self, levels: u8) -> Self {
+        self.config.idle = levels;
+        self
+    }
+
+    pub async fn enable(mut self) -> Result<Generator, crate::Error> {
+        let desc: waveform::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        if let Some(hz) = self.tick_rate {
+            let div = (desc.base_clock.get() as u64 + hz as u64 / 2) / hz.max(1) as u64;
+            self.config.clock_div.set(div.clamp(1, u16::MAX as u64) as u16);
+        }
+
+        let clock_div = self.config.clock_div.get();
+        if clock_div == 0 || clock_div > desc.max_div.get() {
+            Err("clock divider not supported")?
+        }
+
+        if (self.config.idle as u32) >> desc.pin_count.min(8) != 0 {
+            Err("idle levels for pins not controlled by the generator")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        Ok(Generator {
+            resource,
+            desc,
+            clock_div,
+        })
+    }
+}
+
+impl Generator {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Number of pins driven by the waveform.
+    pub fn pin_count(&self) -> u8 {
+        self.desc.pin_count
+    }
+
+    /// Tick frequency in Hz.
+    pub fn tick_rate(&self) -> f64 {
+        self.desc.base_clock.get() as f64 / self.clock_div as f64
+    }
+
+    /// Convert a duration to the nearest number of ticks.
+    pub fn ticks(&self, duration: Duration) -> u32 {
+        (duration.as_secs_f64() * self.tick_rate())
+            .round()
+            .clamp(0.0, u32::MAX as f64) as u32
+    }
+
+    pub fn cmd_load<'a>(
+        &self,
+        offset: u16,
+        entries: &'a [waveform::Entry],
+    ) -> Command<(u16, &'a [waveform::Entry]), ()> {
+        Command::new(self.resource.id, waveform::cmd::LOAD, (offset, entries), ())
+    }
+
+    pub fn cmd_start(&self, len: u16, loops: u16) -> Command<(u16, u16), ()> {
+        Command::new(self.resource.id, waveform::cmd::START, (len, loops), ())
+    }
+
+    pub fn cmd_stop(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, waveform::cmd::STOP, (), ())
+    }
+
+    pub fn cmd_wait(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, waveform::cmd::WAIT, (), ())
+    }
+
+    /// Stop any waveform in progress, then load and start playing `waveform`.
+    ///
+    /// Returns once playback has started.
+    pub async fn play(&self, waveform: &Waveform) -> Result<(), WaveformError> {
+        let entries = &waveform.entries[..];
+        if entries.is_empty() {
+            return Err(WaveformError::Empty);
+        }
+        if entries.len() > self.desc.buffer_len.get() as usize {
+            return Err(WaveformError::TooLong(entries.len()));
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            if (entry.levels as u32) >> self.desc.pin_count.min(8) != 0 {
+                return Err(WaveformError::InvalidLevels(i));
+            }
+            if entry.ticks.get() < self.desc.min_ticks.get() as u32 {
+                return Err(WaveformError::TooShort(i));
+            }
+        }
+
+        let mut queue = self.resource.interface.queue();
+        queue.push(self.cmd_stop()).await;
+        for (i, chunk) in entries.chunks(WAVEFORM_LOAD_LEN).enumerate() {
+            let offset = (i * WAVEFORM_LOAD_LEN) as u16;
+            queue.push(self.cmd_load(offset, chunk)).await;
+        }
+        queue
+            .push(self.cmd_start(entries.len() as u16, waveform.loops))
+            .await;
+        Ok(queue.finish().await?)
+    }
+
+    /// Stop playback and return the pins to their idle levels.
+    pub async fn stop(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_stop()).await
+    }
+
+    /// Wait for a waveform with a finite number of repetitions to finish.
+    pub async fn wait(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_wait()).await
+    }
+}
+
+/// Counts the position of a quadrature encoder.
+pub struct Quadrature {
+    resource: Resource,
+    max_rate: u32,
+}
+
+pub struct QuadratureBuilder {
+    resource: Resource,
+    mode: u8,
+    config: quadrature::Config,
+}
+
+impl ResourceMode for Quadrature {
+    const PROTOCOL: u16 = quadrature::PROTOCOL;
+    type Builder = QuadratureBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        QuadratureBuilder {
+            resource,
+            mode,
+            config: quadrature::Config::default(),
+        }
+    }
+}
+
+impl QuadratureBuilder {
+    /// Count down instead of up when A leads B.
+    pub fn reverse(mut self) -> Self {
+        self.config.flags = self.config.flags.union(quadrature::ConfigFlags::REVERSE);
+        self
+    }
+
+    /// Reset the count to zero on each index pulse.
+    pub fn index_reset(mut self) -> Self {
+        self.config.flags = self
+            .config
+            .flags
+            .union(quadrature::ConfigFlags::INDEX_RESET);
+        self
+    }
+
+    /// Deliver an event with the count on each index pulse.
+    pub fn index_events(mut self) -> Self {
+        self.config.flags = self
+            .config
+            .flags
+            .union(quadrature::ConfigFlags::INDEX_EVENTS);
+        self
+    }
+
+    pub async fn enable(self) -> Result<Quadrature, crate::Error> {
+        use quadrature::{ConfigFlags, ModeFlags};
+
+        let desc: quadrature::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let index = ConfigFlags::INDEX_RESET.union(ConfigFlags::INDEX_EVENTS);
+        if self.config.flags.contains(index) && !desc.flags.contains(ModeFlags::INDEX) {
+            Err("index input not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        if self.config.flags.contains(ConfigFlags::INDEX_EVENTS) {
+            resource.subscribe_events(|_, _| Some(4)).await;
+        }
+
+        Ok(Quadrature {
+            resource,
+            max_rate: desc.max_rate.get(),
+        })
+    }
+}
+
+impl Quadrature {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Maximum rate of counted edges in Hz.
+    pub fn max_rate(&self) -> u32 {
+        self.max_rate
+    }
+
+    pub fn cmd_read(&self) -> Command<(), ScalarResponse<u32>> {
+        Command::new(
+            self.resource.id,
+            quadrature::cmd::READ,
+            (),
+            ScalarResponse::new(),
+        )
+    }
+
+    /// Read the current count. Each edge on either input counts once, so a
+    /// full cycle of the encoder is four counts.
+    pub async fn read(&self) -> Result<i32, RequestError> {
+        Ok(self.resource.interface.run(self.cmd_read()).await? as i32)
+    }
+
+    pub fn cmd_reset(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, quadrature::cmd::RESET, (), ())
+    }
+
+    /// Set the count to zero.
+    pub async fn reset(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_reset()).await
+    }
+
+    /// Wait for the next index pulse when configured for index events,
+    /// returning the count when it occurred.
+    pub async fn next_index(&self) -> Result<i32, RequestError> {
+        let event = self.resource.next_event().await?;
+        match event.evt {
+            quadrature::evt::INDEX => Ok(i32::from_le_bytes(event.data[..4].try_into().unwrap())),
+            _ => Err(RequestError::Protocol("unknown quadrature event")),
+        }
+    }
+}
+
+/// Counts edges on a pin, either over a repeating gate time to measure
+/// frequency or continuously to count pulses.
+pub struct Counter {
+    resource: Resource,
+    gate_time: Duration,
+    both: bool,
+}
+
+pub struct CounterBuilder {
+    resource: Resource,
+    mode: u8,
+    config: counter::Config,
+}
+
+impl ResourceMode for Counter {
+    const PROTOCOL: u16 = counter::PROTOCOL;
+    type Builder = CounterBuilder;
+
+    fn build(resource: Resource, mode: u8) -> Self::Builder {
+        CounterBuilder {
+            resource,
+            mode,
+            config: counter::Config::default(),
+        }
+    }
+}
+
+impl CounterBuilder {
+    /// Count rising edges only (default).
+    pub fn rising(mut self) -> Self {
+        self.config.flags = counter::ConfigFlags::RISING;
+        self
+    }
+
+    /// Count falling edges only.
+    pub fn falling(mut self) -> Self {
+        self.config.flags = counter::ConfigFlags::FALLING;
+        self
+    }
+
+    /// Count both rising and falling edges.
+    pub fn both(mut self) -> Self {
+        self.config.flags = counter::ConfigFlags::RISING.union(counter::ConfigFlags::FALLING);
+        self
+    }
+
+    /// Count over repeating gate periods of `time`. Defaults to 100 ms.
+    pub fn gate_time(mut self, time: Duration) -> Self {
+        let us = time.as_micros().clamp(1, u32::MAX as u128) as u32;
+        self.config.gate_time.set(us);
+        self
+    }
+
+    /// Count continuously from when the counter is enabled or cleared.
+    pub fn totalize(mut self) -> Self {
+        self.config.gate_time.set(0);
+        self
+    }
+
+    pub async fn enable(self) -> Result<Counter, crate::Error> {
+        use counter::{ConfigFlags, ModeFlags};
+
+        let desc: counter::DescribeMode = self
+            .resource
+            .mode_descriptor(self.mode)
+            .ok_or("mode not found")?;
+
+        let rising = self.config.flags.contains(ConfigFlags::RISING);
+        let falling = self.config.flags.contains(ConfigFlags::FALLING);
+        let supported = match (rising, falling) {
+            (true, true) => desc.flags.contains(ModeFlags::BOTH),
+            (true, false) => desc.flags.contains(ModeFlags::RISING),
+            (false, true) => desc.flags.contains(ModeFlags::FALLING),
+            (false, false) => false,
+        };
+        if !supported {
+            Err("edge selection not supported")?
+        }
+
+        let gate = self.config.gate_time.get();
+        if gate == 0 {
+            if !desc.flags.contains(ModeFlags::TOTALIZE) {
+                Err("totalizing not supported")?
+            }
+        } else if gate < desc.min_gate.get() || gate > desc.max_gate.get() {
+            Err("gate time not supported")?
+        }
+
+        let mut resource = self.resource;
+        resource
+            .configure(self.mode, self.config.as_bytes())
+            .await?;
+
+        Ok(Counter {
+            resource,
+            gate_time: Duration::from_micros(gate as u64),
+            both: rising && falling,
+        })
+    }
+}
+
+impl Counter {
+    pub fn id(&self) -> u8 {
+        self.resource.id
+    }
+
+    /// Gate time, or zero if counting continuously.
+    pub fn gate_time(&self) -> Duration {
+        self.gate_time
+    }
+
+    pub fn cmd_read(&self) -> Command<(), ScalarResponse<u32>> {
+        Command::new(
+            self.resource.id,
+            counter::cmd::READ,
+            (),
+            ScalarResponse::new(),
+        )
+    }
+
+    /// Read the number of edges in the most recently completed gate period,
+    /// or the total since enabled or cleared when counting continuously.
+    pub async fn read(&self) -> Result<u32, RequestError> {
+        self.resource.interface.run(self.cmd_read()).await
+    }
+
+    pub fn cmd_clear(&self) -> Command<(), ()> {
+        Command::new(self.resource.id, counter::cmd::CLEAR, (), ())
+    }
+
+    /// Reset the total count, or restart the current gate period.
+    pub async fn clear(&self) -> Result<(), RequestError> {
+        self.resource.interface.run(self.cmd_clear()).await
+    }
+
+    /// Frequency in Hz measured over the most recently completed gate period.
+    ///
+    /// Returns `None` when counting continuously.
+    pub async fn frequency(&self) -> Result<Option<f64>, RequestError> {
+        if self.gate_time.is_zero() {
+            return Ok(None);
+        }
+        let mut count = self.read().await? as f64;
+        if self.both {
+            count /= 2.0;
+        }
+        Ok(Some(count / self.gate_time.as_secs_f64()))
+    }
+}
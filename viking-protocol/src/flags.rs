@@ -5,7 +5,7 @@ macro_rules! flags {
         }
     ) => {
         #[repr(transparent)]
-        #[derive(Copy, Clone, PartialEq, Eq, IntoBytes, FromBytes, Unaligned)]
+        #[derive(Copy, Clone, PartialEq, Eq, IntoBytes, FromBytes, Immutable, Unaligned)]
         $vis struct $name([u8; ::core::mem::size_of::<$int>()]);
 
         impl $name {
@@ -19,6 +19,10 @@ macro_rules! flags {
                 Self((<$int>::from_le_bytes(self.0) | <$int>::from_le_bytes(other.0)).to_le_bytes())
             }
 
+            $vis const fn difference(self, other: Self) -> Self {
+                Self((<$int>::from_le_bytes(self.0) & !<$int>::from_le_bytes(other.0)).to_le_bytes())
+            }
+
             $vis const fn contains(self, other: Self) -> bool {
                 <$int>::from_le_bytes(self.0) & <$int>::from_le_bytes(other.0) != 0
             }
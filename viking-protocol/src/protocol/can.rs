@@ -0,0 +1,109 @@
+use crate::flags::flags;
+use zerocopy::little_endian::U32;
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0800;
+
+flags! {
+    pub struct ModeFlags: u8 {
+        const FD = 1 << 0;
+        const LISTEN_ONLY = 1 << 1;
+        const LOOPBACK = 1 << 2;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    pub flags: ModeFlags,
+    /// Number of acceptance filters
+    pub filter_count: u8,
+    /// Minimum nominal bitrate in bits per second
+    pub min_bitrate: U32,
+    /// Maximum nominal bitrate in bits per second
+    pub max_bitrate: U32,
+    /// Maximum bitrate of the CAN FD data phase, or 0 if FD is not supported
+    pub max_data_bitrate: U32,
+    /// Frequency of the timestamp counter in Hz
+    pub timestamp_clock: U32,
+}
+
+flags! {
+    pub struct ConfigFlags: u8 {
+        const FD = 1 << 0;
+        const LISTEN_ONLY = 1 << 1;
+        const LOOPBACK = 1 << 2;
+    }
+}
+
+/// Followed by `filter_count` [`Filter`]s.
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    pub flags: ConfigFlags,
+    pub filter_count: u8,
+    /// Nominal bitrate in bits per second
+    pub bitrate: U32,
+    /// Bitrate of the CAN FD data phase when the bitrate switch is used
+    pub data_bitrate: U32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            flags: ConfigFlags::EMPTY,
+            filter_count: 0,
+            bitrate: U32::new(500_000),
+            data_bitrate: U32::new(0),
+        }
+    }
+}
+
+/// Bit of an identifier selecting the 29-bit extended format
+pub const ID_EXTENDED: u32 = 1 << 31;
+
+/// Acceptance filter. A frame is accepted if `frame_id & mask == id & mask`,
+/// including the [`ID_EXTENDED`] bit.
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned, Clone, Copy)]
+#[repr(C)]
+pub struct Filter {
+    pub id: U32,
+    pub mask: U32,
+}
+
+/// Frame flags, following the identifier in TRANSMIT and RX
+pub mod frame {
+    pub const REMOTE: u8 = 1 << 0;
+    pub const FD: u8 = 1 << 1;
+    pub const BRS: u8 = 1 << 2;
+
+    /// Size of the frame header: `<id:u32> <flags:u8> <len:u8>`
+    pub const HEADER_LEN: usize = 6;
+}
+
+pub mod state {
+    pub const ERROR_ACTIVE: u8 = 0;
+    pub const ERROR_PASSIVE: u8 = 1;
+    pub const BUS_OFF: u8 = 2;
+}
+
+pub mod error {
+    pub const OTHER: u8 = 0;
+    pub const BIT: u8 = 1;
+    pub const STUFF: u8 = 2;
+    pub const CRC: u8 = 3;
+    pub const FORM: u8 = 4;
+    pub const ACK: u8 = 5;
+    pub const OVERRUN: u8 = 6;
+    pub const BUS_OFF: u8 = 7;
+}
+
+pub mod cmd {
+    pub const TRANSMIT: u8 = 0;
+    pub const STATUS: u8 = 1;
+}
+
+pub mod evt {
+    pub const RX: u8 = 0;
+    pub const ERROR: u8 = 1;
+}
@@ -1,6 +1,64 @@
+use crate::flags::flags;
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
 pub mod pin {
+    use super::*;
+
     pub const PROTOCOL: u16 = 0x0110;
 
+    flags! {
+        pub struct ModeFlags: u8 {
+            const PULL_UP = 1 << 0;
+            const PULL_DOWN = 1 << 1;
+            const OPEN_DRAIN = 1 << 2;
+            const SLEW_RATE = 1 << 3;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Number of selectable drive strength levels, or 0 if not configurable
+        pub drive_levels: u8,
+    }
+
+    pub mod pull {
+        pub const NONE: u8 = 0;
+        pub const UP: u8 = 1;
+        pub const DOWN: u8 = 2;
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const OPEN_DRAIN = 1 << 0;
+            const SLOW_SLEW = 1 << 1;
+        }
+    }
+
+    /// An all-zero configuration selects the device defaults, so devices
+    /// treat a short or empty configuration as zero-padded.
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub pull: u8,
+        pub flags: ConfigFlags,
+        /// 0 for the default drive strength, or 1 to `drive_levels` from
+        /// weakest to strongest
+        pub drive: u8,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                pull: pull::NONE,
+                flags: ConfigFlags::EMPTY,
+                drive: 0,
+            }
+        }
+    }
+
     pub mod cmd {
         pub const FLOAT: u8 = 0;
         pub const READ: u8 = 1;
@@ -24,3 +82,86 @@ pub mod level_interrupt {
         pub const HIGH: u8 = 1;
     }
 }
+
+pub mod edge_interrupt {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0121;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+            const BOTH = 1 << 2;
+            const DEBOUNCE = 1 << 3;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Frequency of the timestamp counter in Hz
+        pub timestamp_clock: U32,
+        /// Maximum debounce time in microseconds
+        pub max_debounce: U16,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+        /// Debounce time in microseconds, or 0 to disable
+        pub debounce: U16,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::RISING.union(ConfigFlags::FALLING),
+                debounce: U16::new(0),
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const DISABLE: u8 = 0;
+        pub const ENABLE: u8 = 1;
+        pub const TIME: u8 = 2;
+    }
+
+    pub mod evt {
+        pub const FALLING: u8 = 0;
+        pub const RISING: u8 = 1;
+        pub const OVERFLOW: u8 = 2;
+    }
+}
+
+pub mod bank {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0140;
+
+    /// Followed by `width` bytes containing the resource ID of the pin at
+    /// each bit position, or 0 if the bit has no pin.
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub width: u8,
+    }
+
+    pub const MAX_WIDTH: u8 = 32;
+
+    pub mod cmd {
+        pub const READ: u8 = 0;
+        pub const WRITE: u8 = 1;
+        pub const SET_DIR: u8 = 2;
+    }
+}
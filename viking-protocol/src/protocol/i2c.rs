@@ -1,5 +1,6 @@
 use crate::flags::flags;
-use zerocopy::{FromBytes, IntoBytes, Unaligned};
+use zerocopy::little_endian::U16;
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
 
 pub mod controller {
     use super::*;
@@ -17,6 +18,8 @@ pub mod controller {
             const ZERO_LEN_WRITE = 1 << 6;
             const ADDR_NACK = 1 << 7;
             const PRECISE_NACK = 1 << 8;
+            /// START accepts 10-bit address header bytes `11110xx`
+            const TEN_BIT = 1 << 9;
         }
     }
 
@@ -48,14 +51,14 @@ pub mod controller {
         }
     }
 
-    #[derive(IntoBytes, FromBytes, Unaligned)]
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
     #[repr(C)]
     pub struct DescribeMode {
         pub flags: ModeFlags,
         pub speed: SpeedFlags,
     }
 
-    #[derive(IntoBytes, FromBytes, Unaligned)]
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
     #[repr(C)]
     pub struct Config {
         pub speed: u8,
@@ -77,6 +80,56 @@ pub mod controller {
     }
 }
 
+pub mod target {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0302;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            const PINS = 1 << 0;
+            const REGISTERS = 1 << 1;
+            const FORWARD = 1 << 2;
+            const TEN_BIT = 1 << 3;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        pub speed: controller::SpeedFlags,
+        /// Size of the register file in bytes
+        pub registers: U16,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const FORWARD = 1 << 0;
+            const TEN_BIT = 1 << 1;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+        pub address: U16,
+    }
+
+    pub mod cmd {
+        pub const LOAD: u8 = 0;
+        pub const READ: u8 = 1;
+        pub const RESPOND: u8 = 2;
+    }
+
+    pub mod evt {
+        pub const WRITE: u8 = 0;
+        pub const READ: u8 = 1;
+        pub const STOP: u8 = 2;
+    }
+}
+
 pub mod scl {
     pub const PROTOCOL: u16 = 0x0310;
 }
@@ -0,0 +1,83 @@
+use crate::flags::flags;
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0A00;
+
+flags! {
+    pub struct ModeFlags: u8 {
+        const TX = 1 << 0;
+        const RX = 1 << 1;
+        /// Can follow an external bit clock and word select
+        const TARGET = 1 << 2;
+    }
+}
+
+flags! {
+    pub struct WordSizes: u8 {
+        const BITS_16 = 1 << 0;
+        const BITS_24 = 1 << 1;
+        const BITS_32 = 1 << 2;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    pub flags: ModeFlags,
+    pub word_sizes: WordSizes,
+    pub max_channels: u8,
+    /// Minimum sample rate in Hz
+    pub min_sample_rate: U32,
+    /// Maximum sample rate in Hz
+    pub max_sample_rate: U32,
+    /// Size of the transmit buffer in bytes
+    pub tx_buffer_len: U16,
+}
+
+flags! {
+    pub struct ConfigFlags: u8 {
+        const TX = 1 << 0;
+        const RX = 1 << 1;
+        const TARGET = 1 << 2;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    pub flags: ConfigFlags,
+    /// Bits per sample: 16, 24 or 32
+    pub word_size: u8,
+    pub channels: u8,
+    /// Sample rate (frames per second) in Hz
+    pub sample_rate: U32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            flags: ConfigFlags::TX,
+            word_size: 16,
+            channels: 2,
+            sample_rate: U32::new(48_000),
+        }
+    }
+}
+
+/// Number of bytes each sample occupies in WRITE and DATA, little endian.
+/// 24-bit samples are right-aligned and sign-extended to 32 bits.
+pub const fn sample_bytes(word_size: u8) -> usize {
+    if word_size <= 16 { 2 } else { 4 }
+}
+
+pub mod cmd {
+    pub const START: u8 = 0;
+    pub const STOP: u8 = 1;
+    pub const WRITE: u8 = 2;
+}
+
+pub mod evt {
+    pub const DATA: u8 = 0;
+    pub const OVERFLOW: u8 = 1;
+}
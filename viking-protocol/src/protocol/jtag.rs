@@ -0,0 +1,40 @@
+use zerocopy::little_endian::U32;
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0910;
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    /// Base clock in Hz from which TCK is divided
+    pub base_clock: U32,
+    /// Maximum clock divider
+    pub max_div: U32,
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    /// TCK frequency is `base_clock / clock_div`
+    pub clock_div: U32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clock_div: U32::new(0),
+        }
+    }
+}
+
+/// Flags byte of SHIFT and SHIFT_OUT
+pub mod shift {
+    /// Drive TMS high on the last bit to leave the Shift-IR / Shift-DR state
+    pub const EXIT: u8 = 1 << 0;
+}
+
+pub mod cmd {
+    pub const TMS: u8 = 0;
+    pub const SHIFT: u8 = 1;
+    pub const SHIFT_OUT: u8 = 2;
+}
@@ -37,3 +37,117 @@ pub mod binary {
         }
     }
 }
+
+pub mod dimmable {
+    use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+    pub const PROTOCOL: u16 = 0x0131;
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        /// See [`super::binary::color`]
+        pub color: u8,
+        /// Number of significant bits in the brightness level, 1 to 16
+        pub bits: u8,
+    }
+
+    pub mod cmd {
+        pub const OFF: u8 = 0;
+        pub const SET: u8 = 1;
+    }
+}
+
+pub mod rgb {
+    use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+    pub const PROTOCOL: u16 = 0x0132;
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        /// Number of significant bits in each channel's intensity, 1 to 16
+        pub bits: u8,
+    }
+
+    pub mod cmd {
+        pub const OFF: u8 = 0;
+        pub const SET: u8 = 1;
+    }
+}
+
+pub mod strip {
+    use crate::flags::flags;
+    use zerocopy::little_endian::U16;
+    use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+    pub const PROTOCOL: u16 = 0x0133;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            /// Pixels have a fourth, white channel
+            const WHITE = 1 << 0;
+        }
+    }
+
+    // Supported timing variants, one bit per value in `timing`
+    flags! {
+        pub struct Timings: u8 {
+            const WS2812 = 1 << 0;
+            const WS2811 = 1 << 1;
+            const SK6812 = 1 << 2;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Order in which color channels are transmitted, see [`order`]
+        pub color_order: u8,
+        pub timings: Timings,
+        /// Maximum number of pixels in the frame buffer
+        pub max_pixels: U16,
+    }
+
+    /// Channel order on the wire. The white channel, if present, is always last.
+    pub mod order {
+        pub const RGB: u8 = 0;
+        pub const RBG: u8 = 1;
+        pub const GRB: u8 = 2;
+        pub const GBR: u8 = 3;
+        pub const BRG: u8 = 4;
+        pub const BGR: u8 = 5;
+    }
+
+    pub mod timing {
+        /// 800 kHz, WS2812 / WS2812B
+        pub const WS2812: u8 = 0;
+        /// 400 kHz, WS2811
+        pub const WS2811: u8 = 1;
+        /// 800 kHz, SK6812
+        pub const SK6812: u8 = 2;
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub timing: u8,
+        /// Number of pixels transmitted by SHOW
+        pub pixels: U16,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                timing: timing::WS2812,
+                pixels: U16::new(0),
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const WRITE_PIXELS: u8 = 0;
+        pub const SHOW: u8 = 1;
+    }
+}
@@ -1,7 +1,15 @@
+pub mod can;
 pub mod gpio;
 pub mod i2c;
+pub mod i2s;
+pub mod jtag;
 pub mod led;
+pub mod onewire;
+pub mod pio;
+pub mod regblock;
 pub mod spi;
+pub mod swd;
+pub mod timer;
 
 /// Base commands
 ///
@@ -14,14 +22,33 @@ pub fn protocol_name(protocol: u16) -> Option<&'static str> {
     Some(match protocol {
         gpio::pin::PROTOCOL => "gpio_pin",
         gpio::level_interrupt::PROTOCOL => "gpio_level_interrupt",
+        gpio::edge_interrupt::PROTOCOL => "gpio_edge_interrupt",
+        gpio::bank::PROTOCOL => "gpio_bank",
         led::binary::PROTOCOL => "led",
+        led::dimmable::PROTOCOL => "led_dimmable",
+        led::rgb::PROTOCOL => "led_rgb",
+        led::strip::PROTOCOL => "led_strip",
         i2c::controller::PROTOCOL => "i2c_controller",
+        i2c::target::PROTOCOL => "i2c_target",
         i2c::scl::PROTOCOL => "i2c_sda_pin",
         i2c::sda::PROTOCOL => "i2c_scl_pin",
+        i2s::PROTOCOL => "i2s",
         spi::controller::PROTOCOL => "spi_controller",
+        spi::target::PROTOCOL => "spi_target",
         spi::sck_pin::PROTOCOL => "spi_sck_pin",
         spi::sdi_pin::PROTOCOL => "spi_sdi_pin",
         spi::sdo_pin::PROTOCOL => "spi_sdo_pin",
+        spi::cs_pin::PROTOCOL => "spi_cs_pin",
+        can::PROTOCOL => "can",
+        jtag::PROTOCOL => "jtag",
+        onewire::PROTOCOL => "onewire",
+        swd::PROTOCOL => "swd",
+        regblock::PROTOCOL => "register_block",
+        pio::PROTOCOL => "rp_pio",
+        timer::capture::PROTOCOL => "timer_capture",
+        timer::waveform::PROTOCOL => "timer_waveform",
+        timer::quadrature::PROTOCOL => "timer_quadrature",
+        timer::counter::PROTOCOL => "timer_counter",
         _ => return None
     })
 }
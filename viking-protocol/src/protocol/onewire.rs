@@ -0,0 +1,43 @@
+use crate::flags::flags;
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0700;
+
+flags! {
+    pub struct ModeFlags: u8 {
+        const STRONG_PULLUP = 1 << 0;
+        const OVERDRIVE = 1 << 1;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    pub flags: ModeFlags,
+}
+
+pub mod speed {
+    pub const STANDARD: u8 = 0;
+    pub const OVERDRIVE: u8 = 1;
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    pub speed: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            speed: speed::STANDARD,
+        }
+    }
+}
+
+pub mod cmd {
+    pub const RESET: u8 = 0;
+    pub const WRITE_BITS: u8 = 1;
+    pub const READ_BITS: u8 = 2;
+    pub const STRONG_PULLUP: u8 = 3;
+}
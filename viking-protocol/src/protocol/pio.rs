@@ -0,0 +1,110 @@
+use crate::flags::flags;
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+/// RP2040 / RP2350 PIO state machine
+pub const PROTOCOL: u16 = 0x1000;
+
+flags! {
+    pub struct ModeFlags: u8 {
+        /// Supports programs using RP2350 (PIO version 1) features
+        const V1 = 1 << 0;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    pub flags: ModeFlags,
+    /// First instruction memory address available to this state machine
+    pub instr_base: u8,
+    /// Number of instruction memory slots available to this state machine
+    pub instr_len: u8,
+    /// GPIO number of pin 0 in the pin mapping configuration
+    pub pin_base: u8,
+    /// Number of pins usable in the pin mapping configuration
+    pub pin_count: u8,
+    /// Depth of each FIFO in words, without joining
+    pub fifo_depth: u8,
+    /// Clock frequency in Hz before the clock divider
+    pub clock: U32,
+}
+
+flags! {
+    pub struct ConfigFlags: u16 {
+        const AUTOPUSH = 1 << 0;
+        const AUTOPULL = 1 << 1;
+        const IN_SHIFT_RIGHT = 1 << 2;
+        const OUT_SHIFT_RIGHT = 1 << 3;
+        const JOIN_TX = 1 << 4;
+        const JOIN_RX = 1 << 5;
+        const OUT_STICKY = 1 << 6;
+    }
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    pub flags: ConfigFlags,
+    /// Integer part of the clock divider
+    pub clock_div_int: U16,
+    /// Fractional part of the clock divider in 1/256ths
+    pub clock_div_frac: u8,
+    /// Autopush threshold in bits, 0 for 32
+    pub push_threshold: u8,
+    /// Autopull threshold in bits, 0 for 32
+    pub pull_threshold: u8,
+    pub out_base: u8,
+    pub out_count: u8,
+    pub set_base: u8,
+    pub set_count: u8,
+    pub in_base: u8,
+    pub sideset_base: u8,
+    pub jmp_pin: u8,
+    /// Bitmap of pins driven as outputs when the mode is configured
+    pub pindirs: U32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            flags: ConfigFlags::IN_SHIFT_RIGHT.union(ConfigFlags::OUT_SHIFT_RIGHT),
+            clock_div_int: U16::new(1),
+            clock_div_frac: 0,
+            push_threshold: 0,
+            pull_threshold: 0,
+            out_base: 0,
+            out_count: 0,
+            set_base: 0,
+            set_count: 0,
+            in_base: 0,
+            sideset_base: 0,
+            jmp_pin: 0,
+            pindirs: U32::new(0),
+        }
+    }
+}
+
+/// Side-set configuration byte of the `LOAD` command
+pub mod sideset {
+    /// Number of side-set bits including the enable bit
+    pub const COUNT_MASK: u8 = 0b111;
+    pub const OPT: u8 = 1 << 3;
+    pub const PINDIRS: u8 = 1 << 4;
+}
+
+pub mod cmd {
+    pub const LOAD: u8 = 0;
+    pub const CONTROL: u8 = 1;
+    pub const PUSH: u8 = 2;
+    pub const PULL: u8 = 3;
+}
+
+/// Operations of the `CONTROL` command
+pub mod control {
+    pub const DISABLE: u8 = 0;
+    pub const ENABLE: u8 = 1;
+    pub const RESTART: u8 = 2;
+    pub const EXEC: u8 = 3;
+    pub const CLEAR_FIFOS: u8 = 4;
+}
@@ -0,0 +1,36 @@
+use crate::flags::flags;
+use zerocopy::little_endian::U32;
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0500;
+
+flags! {
+    pub struct WidthFlags: u8 {
+        const WIDTH_8 = 1 << 0;
+        const WIDTH_16 = 1 << 1;
+        const WIDTH_32 = 1 << 2;
+    }
+}
+
+/// Followed by `read_only_ranges` instances of [`ReadOnlyRange`].
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    pub base: U32,
+    pub size: U32,
+    pub widths: WidthFlags,
+    pub read_only_ranges: u8,
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct ReadOnlyRange {
+    pub offset: U32,
+    pub len: U32,
+}
+
+pub mod cmd {
+    pub const READ32: u8 = 0;
+    pub const WRITE32: u8 = 1;
+    pub const MODIFY: u8 = 2;
+}
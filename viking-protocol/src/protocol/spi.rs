@@ -1,6 +1,6 @@
 use crate::flags::flags;
-use zerocopy::little_endian::U32;
-use zerocopy::{FromBytes, IntoBytes, Unaligned};
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
 
 pub mod controller {
     use super::*;
@@ -16,15 +16,21 @@ pub mod controller {
             const MODE3 = 1 << 5;
             const MSB_FIRST = 1 << 6;
             const LSB_FIRST = 1 << 7;
+            const DUAL = 1 << 8;
+            const QUAD = 1 << 9;
         }
     }
 
-    #[derive(IntoBytes, FromBytes, Unaligned)]
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
     #[repr(C)]
     pub struct DescribeMode {
         pub flags: ModeFlags,
         pub base_clock: U32,
         pub max_div: U32,
+        /// Minimum bits per word, or 0 if only 8-bit words are supported
+        pub min_word_bits: u8,
+        /// Maximum bits per word, or 0 if only 8-bit words are supported
+        pub max_word_bits: u8,
     }
 
     flags! {
@@ -51,11 +57,13 @@ pub mod controller {
         }
     }
 
-    #[derive(IntoBytes, FromBytes, Unaligned)]
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
     #[repr(C)]
     pub struct Config {
         pub flags: ConfigFlags,
         pub clock_div: U32,
+        /// Bits per word, or 0 for 8
+        pub word_bits: u8,
     }
 
     impl Default for Config {
@@ -63,15 +71,117 @@ pub mod controller {
             Self {
                 flags: ConfigFlags::EMPTY,
                 clock_div: U32::new(0),
+                word_bits: 8,
             }
         }
     }
 
+    /// Number of bytes each word occupies in READ, WRITE and TRANSFER data,
+    /// little endian and right-aligned.
+    pub const fn word_bytes(word_bits: u8) -> usize {
+        match word_bits {
+            0..=8 => 1,
+            9..=16 => 2,
+            _ => 4,
+        }
+    }
+
     pub mod cmd {
+        pub const MULTI: u8 = 0;
         pub const READ: u8 = 1;
         pub const WRITE: u8 = 2;
         pub const TRANSFER: u8 = 3;
     }
+
+    /// Number of data lines used by a phase of a MULTI command
+    pub mod lanes {
+        pub const NONE: u8 = 0;
+        pub const SINGLE: u8 = 1;
+        pub const DUAL: u8 = 2;
+        pub const QUAD: u8 = 3;
+
+        pub const INSTRUCTION_SHIFT: u8 = 0;
+        pub const ADDRESS_SHIFT: u8 = 2;
+        pub const DATA_SHIFT: u8 = 4;
+        pub const MASK: u8 = 0b11;
+    }
+
+    /// Flag bits of a MULTI command in addition to the lane widths
+    pub mod multi {
+        /// Address phase is 4 bytes instead of 3
+        pub const ADDRESS_4BYTE: u8 = 1 << 6;
+        /// Data phase writes instead of reads
+        pub const WRITE: u8 = 1 << 7;
+    }
+
+    /// Header of a MULTI command, followed by `len` bytes of data for a write
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct MultiHeader {
+        pub flags: u8,
+        pub instruction: u8,
+        pub address: U32,
+        pub dummy_cycles: u8,
+        pub len: U16,
+    }
+}
+
+pub mod target {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0201;
+
+    pub use super::controller::ConfigFlags;
+
+    flags! {
+        pub struct ModeFlags: u16 {
+            const PINS = 1 << 0;
+            const MODE0 = 1 << 2;
+            const MODE1 = 1 << 3;
+            const MODE2 = 1 << 4;
+            const MODE3 = 1 << 5;
+            const MSB_FIRST = 1 << 6;
+            const LSB_FIRST = 1 << 7;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Size of the response buffer in bytes
+        pub buffer_len: U16,
+        /// Maximum SCK frequency in Hz
+        pub max_clock: U32,
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+        /// Byte shifted out after the end of the response
+        pub fill: u8,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::EMPTY,
+                fill: 0xff,
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const LOAD: u8 = 0;
+        pub const SET_LEN: u8 = 1;
+    }
+
+    pub mod evt {
+        pub const DATA: u8 = 0;
+        pub const END: u8 = 1;
+        pub const OVERFLOW: u8 = 2;
+    }
 }
 
 pub mod sck_pin {
@@ -85,3 +195,58 @@ pub mod sdo_pin {
 pub mod sdi_pin {
     pub const PROTOCOL: u16 = 0x0212;
 }
+
+/// Chip select driven by the SPI controller
+pub mod cs_pin {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0213;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            /// Chip select can be active high
+            const ACTIVE_HIGH = 1 << 0;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Maximum setup and hold delay in nanoseconds
+        pub max_delay: U16,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const ACTIVE_HIGH = 1 << 0;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+        /// Minimum delay from asserting chip select to the first SCK edge in
+        /// nanoseconds
+        pub setup: U16,
+        /// Minimum delay from the last SCK edge to releasing chip select in
+        /// nanoseconds
+        pub hold: U16,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::EMPTY,
+                setup: U16::new(0),
+                hold: U16::new(0),
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const ASSERT: u8 = 0;
+        pub const RELEASE: u8 = 1;
+    }
+}
@@ -0,0 +1,62 @@
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub const PROTOCOL: u16 = 0x0900;
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct DescribeMode {
+    /// Base clock in Hz from which SWCLK is divided
+    pub base_clock: U32,
+    /// Maximum clock divider
+    pub max_div: U32,
+}
+
+#[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Config {
+    /// SWCLK frequency is `base_clock / clock_div`
+    pub clock_div: U32,
+    /// Turnaround period in clock cycles, 1 to 4
+    pub turnaround: u8,
+    /// Idle cycles with SWDIO low inserted after each transfer
+    pub idle_cycles: u8,
+    /// Number of times a transfer is retried after a WAIT response
+    pub wait_retries: U16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clock_div: U32::new(0),
+            turnaround: 1,
+            idle_cycles: 0,
+            wait_retries: U16::new(100),
+        }
+    }
+}
+
+/// Request byte of READ and WRITE
+pub mod request {
+    /// Access an access port register instead of a debug port register
+    pub const APNDP: u8 = 1 << 0;
+    /// Register address bits A[3:2]
+    pub const ADDR_MASK: u8 = 0b1100;
+}
+
+/// Acknowledge byte returned by READ and WRITE
+pub mod ack {
+    pub const OK: u8 = 0b001;
+    pub const WAIT: u8 = 0b010;
+    pub const FAULT: u8 = 0b100;
+    pub const MASK: u8 = 0b111;
+
+    /// Read data parity mismatch
+    pub const PARITY_ERROR: u8 = 1 << 3;
+}
+
+pub mod cmd {
+    pub const SEQUENCE: u8 = 0;
+    pub const READ: u8 = 1;
+    pub const WRITE: u8 = 2;
+}
@@ -0,0 +1,235 @@
+use crate::flags::flags;
+use zerocopy::little_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, Unaligned};
+
+pub mod capture {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0600;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+            const BOTH = 1 << 2;
+            const EVENTS = 1 << 3;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Frequency of the timestamp counter in Hz
+        pub timestamp_clock: U32,
+        /// Number of entries in the capture buffer
+        pub buffer_len: U16,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+            const EVENTS = 1 << 2;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::RISING.union(ConfigFlags::FALLING),
+            }
+        }
+    }
+
+    /// Bit of a capture entry containing the pin level after the edge
+    pub const ENTRY_LEVEL: u32 = 1 << 31;
+
+    /// Bits of a capture entry containing the timestamp
+    pub const ENTRY_TIMESTAMP: u32 = !ENTRY_LEVEL;
+
+    pub mod cmd {
+        pub const START: u8 = 0;
+        pub const STOP: u8 = 1;
+        pub const READ: u8 = 2;
+        pub const SAMPLE: u8 = 3;
+    }
+
+    pub mod evt {
+        pub const DATA: u8 = 0;
+        pub const OVERFLOW: u8 = 1;
+    }
+}
+
+pub mod waveform {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0610;
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        /// Number of pins driven by the waveform, at most 8
+        pub pin_count: u8,
+        /// Number of entries in the waveform buffer
+        pub buffer_len: U16,
+        /// Base clock frequency in Hz
+        pub base_clock: U32,
+        /// Maximum clock divider
+        pub max_div: U16,
+        /// Minimum duration of an entry in ticks
+        pub min_ticks: U16,
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        /// Divider from the base clock to the tick rate
+        pub clock_div: U16,
+        /// Bitmap of pin levels when the waveform is not playing
+        pub idle: u8,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                clock_div: U16::new(1),
+                idle: 0,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Entry {
+        /// Bitmap of pin levels
+        pub levels: u8,
+        /// Duration in ticks
+        pub ticks: U32,
+    }
+
+    pub mod cmd {
+        pub const LOAD: u8 = 0;
+        pub const START: u8 = 1;
+        pub const STOP: u8 = 2;
+        pub const WAIT: u8 = 3;
+    }
+}
+
+pub mod quadrature {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0620;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            /// Has an index input
+            const INDEX = 1 << 0;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Maximum rate of counted edges in Hz
+        pub max_rate: U32,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            /// Count down when A leads B
+            const REVERSE = 1 << 0;
+            /// Reset the count to zero on each index pulse
+            const INDEX_RESET = 1 << 1;
+            /// Emit an event on each index pulse
+            const INDEX_EVENTS = 1 << 2;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::EMPTY,
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const READ: u8 = 0;
+        pub const RESET: u8 = 1;
+    }
+
+    pub mod evt {
+        pub const INDEX: u8 = 0;
+    }
+}
+
+pub mod counter {
+    use super::*;
+
+    pub const PROTOCOL: u16 = 0x0630;
+
+    flags! {
+        pub struct ModeFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+            const BOTH = 1 << 2;
+            /// Counting without a gate is supported
+            const TOTALIZE = 1 << 3;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct DescribeMode {
+        pub flags: ModeFlags,
+        /// Maximum rate of counted edges in Hz
+        pub max_rate: U32,
+        /// Minimum gate time in microseconds
+        pub min_gate: U32,
+        /// Maximum gate time in microseconds
+        pub max_gate: U32,
+    }
+
+    flags! {
+        pub struct ConfigFlags: u8 {
+            const RISING = 1 << 0;
+            const FALLING = 1 << 1;
+        }
+    }
+
+    #[derive(IntoBytes, FromBytes, Immutable, Unaligned)]
+    #[repr(C)]
+    pub struct Config {
+        pub flags: ConfigFlags,
+        /// Gate time in microseconds, or 0 to count continuously
+        pub gate_time: U32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                flags: ConfigFlags::RISING,
+                gate_time: U32::new(100_000),
+            }
+        }
+    }
+
+    pub mod cmd {
+        pub const READ: u8 = 0;
+        pub const CLEAR: u8 = 1;
+    }
+}